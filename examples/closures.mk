@@ -0,0 +1,13 @@
+// Functions capture the environment they are defined in.
+let adder = fn(x) {
+    fn(y) { x + y }
+};
+let addTwo = adder(2);
+let addTen = adder(10);
+
+puts(addTwo(3));
+puts(addTen(addTwo(30)));
+
+let twice = fn(f, x) { f(f(x)) };
+puts(twice(addTen, 1));
+puts(twice);
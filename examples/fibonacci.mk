@@ -0,0 +1,8 @@
+let fib = fn(n) {
+    if (n < 2) {
+        return n;
+    }
+    fib(n - 1) + fib(n - 2)
+};
+
+puts(fib(0), fib(1), fib(10), fib(20));
@@ -0,0 +1,4 @@
+// The smallest program that prints something.
+puts("Hello, world!");
+print("no", "newline");
+puts("");
@@ -0,0 +1,7 @@
+let greet = fn(name) {
+    "Hello, " + name + "!"
+};
+
+puts(greet("Monkey"));
+puts("tab\tseparated", "quote: \"q\"");
+puts("a" == "a", "a" != "b");
@@ -0,0 +1,5 @@
+// How each kind of value is printed.
+puts(1, -20, true, !true, null);
+puts(fn(a, b) { a * b });
+puts(if (1 > 2) { "yes" });
+puts(puts);
@@ -0,0 +1,620 @@
+use super::STACK_SIZE;
+use crate::{
+    formatter::{self, Config},
+    optimize::OptLevel,
+    repl::Repl,
+    Debugger, Error, Interpreter, IoPolicy, RuntimeError,
+};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Read},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+// Runs of a benchmark when `-n` is not given.
+const BENCH_RUNS: usize = 10;
+
+const USAGE: &str = "usage:
+    interpreter-rust [-O0|-O1]                 start the REPL
+    interpreter-rust run [-O0|-O1] [--trace[=LOG]] FILE
+                                               run a program, tracing it to stderr or LOG
+    interpreter-rust test [-O0|-O1] PATH...    run the tests declared in files or directories
+    interpreter-rust debug FILE                run a program in the debugger
+    interpreter-rust bench [-O0|-O1] [-n N] FILE
+    interpreter-rust fmt [--check] [--width N] [--indent N] [files...]";
+
+/// Runs the command line described by `args` (without the program name)
+/// and returns the process exit code. Programs run on a thread of their
+/// own with a stack big enough for the calls `super::limits` allows.
+pub fn run(args: Vec<String>) -> i32 {
+    let command = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || dispatch(&args));
+    match command.map(|command| command.join()) {
+        Ok(Ok(code)) => code,
+        Ok(Err(panic)) => std::panic::resume_unwind(panic),
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+fn dispatch(args: &[String]) -> i32 {
+    let result = match args.first().map(String::as_str) {
+        Some("fmt") => fmt(&args[1..]),
+        Some("run") => run_file(&args[1..]),
+        Some("test") => test(&args[1..]),
+        Some("debug") => debug(&args[1..]),
+        Some("bench") => bench(&args[1..]),
+        _ => repl(args),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            2
+        }
+    }
+}
+
+fn repl(args: &[String]) -> Result<i32, String> {
+    let mut opt_level = OptLevel::default();
+    for arg in args {
+        opt_level = OptLevel::from_flag(arg).ok_or(format!("unknown argument: {}", arg))?;
+    }
+
+    match Repl::new().with_opt_level(opt_level).start() {
+        Ok(()) => Ok(0),
+        Err(e) => {
+            eprintln!("{}", e);
+            Ok(1)
+        }
+    }
+}
+
+// Runs a program with full access to I/O. The exit code is 1 if it
+// fails to parse or stops with an error.
+fn run_file(args: &[String]) -> Result<i32, String> {
+    let mut opt_level = OptLevel::default();
+    let mut file = None;
+    // `Some(None)` traces to stderr
+    let mut trace = None;
+    for arg in args {
+        match OptLevel::from_flag(arg) {
+            Some(level) => opt_level = level,
+            None if arg == "--trace" => trace = Some(None),
+            None if arg.starts_with("--trace=") => trace = Some(Some(&arg["--trace=".len()..])),
+            None if arg.starts_with('-') || file.is_some() => {
+                return Err(format!("unknown argument: {}", arg))
+            }
+            None => file = Some(arg),
+        }
+    }
+    let file = file.ok_or("run expects a file")?;
+
+    let input = match fs::read_to_string(file) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("{}: {}", file, e);
+            return Ok(1);
+        }
+    };
+
+    let mut interpreter = Interpreter::new()
+        .with_limits(super::limits())
+        .with_opt_level(opt_level)
+        .with_io_policy(IoPolicy::allow_all());
+    match trace {
+        Some(Some(log)) => match File::create(log) {
+            Ok(log) => interpreter = interpreter.with_trace(BufWriter::new(log)),
+            Err(e) => {
+                eprintln!("{}: {}", log, e);
+                return Ok(1);
+            }
+        },
+        Some(None) => interpreter = interpreter.with_trace(io::stderr()),
+        None => {}
+    }
+    match interpreter.eval(&input) {
+        Ok(_) => Ok(0),
+        Err(Error::Parse(errors) | Error::Resolve(errors)) => {
+            print_errors(file, &errors);
+            Ok(1)
+        }
+        Err(e) => {
+            print_error(file, &e);
+            Ok(1)
+        }
+    }
+}
+
+// Runs a program under the debugger, unoptimized so that every
+// statement is where the source has it.
+fn debug(args: &[String]) -> Result<i32, String> {
+    let file = match args {
+        [file] if !file.starts_with('-') => file,
+        [] => return Err(String::from("debug expects a file")),
+        [arg, ..] => return Err(format!("unknown argument: {}", arg)),
+    };
+    let input = match fs::read_to_string(file) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("{}: {}", file, e);
+            return Ok(1);
+        }
+    };
+
+    println!("debugging {}; type help for the commands", file);
+    let mut interpreter = Interpreter::new()
+        .with_limits(super::limits())
+        .with_io_policy(IoPolicy::allow_all())
+        .with_debugger(Debugger::new(&input));
+    match interpreter.eval(&input) {
+        Ok(_) => Ok(0),
+        Err(Error::Parse(errors) | Error::Resolve(errors)) => {
+            print_errors(file, &errors);
+            Ok(1)
+        }
+        Err(e) => {
+            print_error(file, &e);
+            Ok(1)
+        }
+    }
+}
+
+// A test that failed, with what to report about it.
+struct Failure {
+    name: String,
+    location: String,
+    message: String,
+}
+
+// Runs every file, then the `test` declarations it made, and reports
+// each test followed by the failures. The exit code is 1 if a test fails
+// or a file can't be run.
+fn test(args: &[String]) -> Result<i32, String> {
+    let mut opt_level = OptLevel::default();
+    let mut paths = vec![];
+    for arg in args {
+        match OptLevel::from_flag(arg) {
+            Some(level) => opt_level = level,
+            None if arg.starts_with('-') => return Err(format!("unknown argument: {}", arg)),
+            None => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        return Err(String::from("test expects a file or directory"));
+    }
+
+    let mut files = vec![];
+    for path in &paths {
+        if let Err(e) = collect_programs(path, &mut files) {
+            eprintln!("{}: {}", path.display(), e);
+            return Ok(1);
+        }
+    }
+
+    let report = run_tests(&files, opt_level);
+    if !report.failures.is_empty() {
+        println!("\nfailures:");
+        for failure in &report.failures {
+            println!("\n---- {} ({}) ----", failure.name, failure.location);
+            println!("{}", failure.message);
+        }
+    }
+    let code = report.code();
+    let result = if code == 0 { "ok" } else { "FAILED" };
+    println!(
+        "\ntest result: {}. {} passed; {} failed",
+        result,
+        report.passed,
+        report.failures.len()
+    );
+    Ok(code)
+}
+
+// What running the tests declared in some files came to.
+struct Report {
+    passed: usize,
+    failures: Vec<Failure>,
+    // whether a file could not be read or run
+    broken: bool,
+}
+
+impl Report {
+    fn code(&self) -> i32 {
+        i32::from(self.broken || !self.failures.is_empty())
+    }
+}
+
+// Runs every file, then the tests it declared, reporting each test as it
+// finishes.
+fn run_tests(files: &[PathBuf], opt_level: OptLevel) -> Report {
+    let mut report = Report {
+        passed: 0,
+        failures: vec![],
+        broken: false,
+    };
+    for file in files {
+        let name = file.display().to_string();
+        let input = match fs::read_to_string(file) {
+            Ok(input) => input,
+            Err(e) => {
+                eprintln!("{}: {}", name, e);
+                report.broken = true;
+                continue;
+            }
+        };
+
+        let mut interpreter = Interpreter::new()
+            .with_limits(super::limits())
+            .with_opt_level(opt_level)
+            .with_io_policy(IoPolicy::allow_all());
+        match interpreter.eval(&input) {
+            Ok(_) => {}
+            Err(Error::Parse(errors) | Error::Resolve(errors)) => {
+                print_errors(&name, &errors);
+                report.broken = true;
+                continue;
+            }
+            Err(e) => {
+                print_error(&name, &e);
+                report.broken = true;
+                continue;
+            }
+        }
+
+        let tests = interpreter.take_tests();
+        println!("running {} tests in {}", tests.len(), name);
+        for test in tests {
+            match interpreter.run_test(&test) {
+                Ok(()) => {
+                    println!("test {} ... ok", test.name);
+                    report.passed += 1;
+                }
+                Err(e) => {
+                    println!("test {} ... FAILED", test.name);
+                    report.failures.push(Failure {
+                        name: test.name.clone(),
+                        location: format!("{}:{}", name, test.line),
+                        message: failure_message(&name, e),
+                    });
+                }
+            }
+        }
+    }
+    report
+}
+
+// Points at the line of the failed assertion when it is known, followed
+// by the calls it happened in.
+fn failure_message(file: &str, e: Error) -> String {
+    let mut message = match &e {
+        Error::Runtime(RuntimeError {
+            message,
+            line: Some(line),
+            ..
+        }) => format!("{}:{}: {}", file, line, message),
+        e => format!("{}: {}", file, e),
+    };
+    for frame in e.backtrace() {
+        message.push_str(&format!("\n    at {}", frame));
+    }
+    message
+}
+
+// Runs a program `-n` times, each in a fresh interpreter with its output
+// discarded, and reports the fastest, median and slowest wall time and
+// the steps one run takes. The exit code is 1 if the program fails.
+fn bench(args: &[String]) -> Result<i32, String> {
+    let mut opt_level = OptLevel::default();
+    let mut runs = BENCH_RUNS;
+    let mut file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match OptLevel::from_flag(arg) {
+            Some(level) => opt_level = level,
+            None if arg == "-n" => runs = parse_number(arg, args.next())?,
+            None if arg.starts_with('-') || file.is_some() => {
+                return Err(format!("unknown argument: {}", arg))
+            }
+            None => file = Some(arg),
+        }
+    }
+    let file = file.ok_or("bench expects a file")?;
+    if runs == 0 {
+        return Err(String::from("-n expects at least 1 run"));
+    }
+
+    let input = match fs::read_to_string(file) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("{}: {}", file, e);
+            return Ok(1);
+        }
+    };
+
+    let mut times = vec![];
+    let mut steps = 0;
+    for _ in 0..runs {
+        let mut interpreter = Interpreter::new()
+            .with_limits(super::limits())
+            .with_opt_level(opt_level)
+            .with_io_policy(IoPolicy::allow_all())
+            .with_output(io::sink());
+        let start = Instant::now();
+        let result = interpreter.eval(&input);
+        times.push(start.elapsed());
+        steps = interpreter.steps();
+        match result {
+            Ok(_) => {}
+            Err(Error::Parse(errors) | Error::Resolve(errors)) => {
+                print_errors(file, &errors);
+                return Ok(1);
+            }
+            Err(e) => {
+                print_error(file, &e);
+                return Ok(1);
+            }
+        }
+    }
+
+    times.sort();
+    println!("{}: {} runs, {} steps each", file, runs, steps);
+    println!(
+        "min: {:?}, median: {:?}, max: {:?}",
+        times[0],
+        median(&times),
+        times[times.len() - 1]
+    );
+    Ok(0)
+}
+
+// The middle of `times`, which are sorted and not empty.
+fn median(times: &[Duration]) -> Duration {
+    let mid = times.len() / 2;
+    if times.len().is_multiple_of(2) {
+        (times[mid - 1] + times[mid]) / 2
+    } else {
+        times[mid]
+    }
+}
+
+// Adds `path` if it is a file, or the `.mk` files below it if it is a
+// directory, in a stable order.
+fn collect_programs(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            collect_programs(&entry, files)?;
+        } else if entry.extension().is_some_and(|ext| ext == "mk") {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+// Formats files in place, or stdin to stdout when no file is given. With
+// `--check` nothing is written and the exit code is 1 if any file would
+// change.
+fn fmt(args: &[String]) -> Result<i32, String> {
+    let mut check = false;
+    let mut config = Config::default();
+    let mut files = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--width" => config.line_width = parse_number(arg, args.next())?,
+            "--indent" => config.indent_width = parse_number(arg, args.next())?,
+            _ if arg.starts_with('-') => return Err(format!("unknown argument: {}", arg)),
+            _ => files.push(arg.clone()),
+        }
+    }
+
+    if files.is_empty() {
+        let mut input = String::new();
+        io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|e| e.to_string())?;
+        return match formatter::format_source(&input, &config) {
+            Ok(out) if check => Ok(i32::from(out != input)),
+            Ok(out) => {
+                print!("{}", out);
+                Ok(0)
+            }
+            Err(errors) => {
+                print_errors("<stdin>", &errors);
+                Ok(1)
+            }
+        };
+    }
+
+    let mut code = 0;
+    for file in files {
+        let input = match fs::read_to_string(&file) {
+            Ok(input) => input,
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                code = 1;
+                continue;
+            }
+        };
+
+        match formatter::format_source(&input, &config) {
+            Ok(out) if out == input => {}
+            Ok(_) if check => {
+                println!("{}: not formatted", file);
+                code = 1;
+            }
+            Ok(out) => {
+                if let Err(e) = fs::write(&file, out) {
+                    eprintln!("{}: {}", file, e);
+                    code = 1;
+                }
+            }
+            Err(errors) => {
+                print_errors(&file, &errors);
+                code = 1;
+            }
+        }
+    }
+    Ok(code)
+}
+
+fn parse_number(flag: &str, value: Option<&String>) -> Result<usize, String> {
+    value
+        .and_then(|v| v.parse().ok())
+        .ok_or(format!("{} expects a number", flag))
+}
+
+// Prints a failed evaluation with the backtrace of a runtime error
+// beneath it.
+fn print_error(file: &str, e: &Error) {
+    eprintln!("{}: {}", file, e);
+    for frame in e.backtrace() {
+        eprintln!("    at {}", frame);
+    }
+}
+
+fn print_errors(file: &str, errors: &[String]) {
+    for e in errors {
+        eprintln!("{}: {}", file, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{utils::scratch_dir, Frame};
+
+    const FAILING: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/failing_test.mk"
+    );
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_collect_programs() {
+        let dir = scratch_dir("collect-programs");
+        fs::create_dir(dir.join("nested")).unwrap();
+        for file in ["b.mk", "a.mk", "notes.txt", "nested/c.mk"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+
+        let mut files = vec![];
+        collect_programs(&dir, &mut files).unwrap();
+        collect_programs(&dir.join("notes.txt"), &mut files).unwrap();
+        let expected: Vec<PathBuf> = ["a.mk", "b.mk", "nested/c.mk", "notes.txt"]
+            .iter()
+            .map(|file| dir.join(file))
+            .collect();
+        assert_eq!(files, expected);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_failure_message() {
+        let err = RuntimeError {
+            line: Some(3),
+            backtrace: vec![Frame {
+                function: String::from("check"),
+                line: Some(7),
+                args: vec![String::from("2")],
+            }],
+            ..RuntimeError::new("assertion failed")
+        };
+        assert_eq!(
+            failure_message("t.mk", Error::Runtime(err)),
+            "t.mk:3: assertion failed\n    at check(2) called on line 7"
+        );
+        assert_eq!(
+            failure_message("t.mk", Error::Runtime(RuntimeError::new("oops"))),
+            "t.mk: oops"
+        );
+    }
+
+    #[test]
+    fn test_failing_test_file() {
+        let report = run_tests(&[PathBuf::from(FAILING)], OptLevel::default());
+        assert_eq!(report.passed, 1);
+        assert!(!report.broken);
+        assert_eq!(report.failures.len(), 1);
+
+        let failure = &report.failures[0];
+        assert_eq!(failure.name, "checks");
+        assert_eq!(failure.location, format!("{}:8", FAILING));
+        assert_eq!(
+            failure.message,
+            format!(
+                "{}:9: assertion failed: left == right\n  left: 4\n right: 5\n    at check(2) called on line 10",
+                FAILING
+            )
+        );
+
+        assert_eq!(report.code(), 1);
+        assert_eq!(test(&args(&[FAILING])), Ok(1));
+        assert_eq!(test(&args(&["-O0", FAILING])), Ok(1));
+        assert_eq!(
+            test(&args(&["--bogus", FAILING])),
+            Err(String::from("unknown argument: --bogus"))
+        );
+        assert!(test(&[]).is_err());
+    }
+
+    #[test]
+    fn test_missing_file_fails_the_run() {
+        let report = run_tests(&[PathBuf::from("missing.mk")], OptLevel::default());
+        assert!(report.broken);
+        assert_eq!(report.code(), 1);
+        assert_eq!(run_tests(&[], OptLevel::default()).code(), 0);
+    }
+
+    #[test]
+    fn test_median() {
+        let ms = Duration::from_millis;
+        assert_eq!(median(&[ms(4)]), ms(4));
+        assert_eq!(median(&[ms(1), ms(2), ms(9)]), ms(2));
+        assert_eq!(median(&[ms(1), ms(2), ms(4), ms(9)]), ms(3));
+    }
+
+    #[test]
+    fn test_bench_arguments() {
+        let tests = vec![
+            (vec![], "bench expects a file"),
+            (vec!["-n", "0", "a.mk"], "-n expects at least 1 run"),
+            (vec!["-n", "many", "a.mk"], "-n expects a number"),
+            (vec!["a.mk", "-n"], "-n expects a number"),
+            (vec!["a.mk", "b.mk"], "unknown argument: b.mk"),
+            (vec!["--fast", "a.mk"], "unknown argument: --fast"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                bench(&args(&input)),
+                Err(String::from(expected)),
+                "args: {:?}",
+                input
+            );
+        }
+
+        assert_eq!(bench(&args(&["-n", "2", "missing.mk"])), Ok(1));
+        assert_eq!(bench(&args(&["-O0", "-n", "2", FAILING])), Ok(0));
+    }
+}
@@ -1,28 +1,48 @@
-use crate::{
-    evaluator::object::Object,
-    evaluator::{environment::Environment, eval},
-    lexer::Lexer,
-    parser::Parser,
-};
-use std::rc::Rc;
+pub mod cli;
+
+/// Native stack of the thread the command line runs programs on. Every
+/// call of a program takes a good few kilobytes of it, more so in a debug
+/// build, so the main thread's stack would only fit a few hundred.
+pub(crate) const STACK_SIZE: usize = 256 << 20;
+
+// Deepest nesting of calls allowed, well within `STACK_SIZE`.
+const MAX_DEPTH: usize = 10_000;
+
+/// Limits of the programs run by the command line and the REPL, so that
+/// runaway recursion stops with an error instead of overflowing the
+/// stack.
+pub(crate) fn limits() -> Limits {
+    Limits {
+        max_depth: Some(MAX_DEPTH),
+        ..Limits::default()
+    }
+}
+
+use crate::{evaluator::object::Object, optimize::OptLevel, Interpreter, IoPolicy, Limits};
+
 pub struct App {
     input: String,
+    opt_level: OptLevel,
 }
 
 impl App {
     pub fn new<T: Into<String>>(input: T) -> Self {
         App {
             input: input.into(),
+            opt_level: OptLevel::default(),
         }
     }
 
-    pub fn execute(&self) -> Option<Object> {
-        let lexer = Lexer::new(self.input.as_str());
-        let mut parser = Parser::new(lexer);
-
-        let prog = parser.parse_program();
-        let mut env = Environment::new();
+    pub fn with_opt_level(mut self, opt_level: OptLevel) -> Self {
+        self.opt_level = opt_level;
+        self
+    }
 
-        eval(&prog, Rc::clone(&env))
+    pub fn execute(&self) -> Option<Object> {
+        Interpreter::new()
+            .with_opt_level(self.opt_level)
+            .with_io_policy(IoPolicy::allow_all())
+            .eval(&self.input)
+            .ok()
     }
 }
@@ -0,0 +1,384 @@
+use super::{
+    apply_function, apply_hooked,
+    debugger::Debugger,
+    environment::{Environment, Meter},
+    io::IoPolicy,
+    object::*,
+    trace::Tracer,
+};
+use crate::parser::ast::Node;
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{self, BufRead, Write},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// Bounds on the resources a program may use, for running untrusted
+/// code. Every limit is off by default. The limits apply to each call of
+/// `Interpreter::eval` separately, except `memory`, which bounds what is
+/// alive at any point.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    /// Number of evaluation steps, one per node evaluated.
+    pub fuel: Option<u64>,
+    /// Memory alive at once, in units of one per environment and
+    /// binding and one per byte of a string or element of an array or
+    /// hash (see `Object::size`).
+    pub memory: Option<usize>,
+    /// Wall-clock time allowed for one evaluation.
+    pub timeout: Option<Duration>,
+    /// Depth of nested function calls. Deep recursion overflows the
+    /// native stack long before it runs out of fuel or memory.
+    pub max_depth: Option<usize>,
+}
+
+/// The limit a program ran into. Evaluation stops as soon as a limit is
+/// exceeded and cannot be resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceExhausted {
+    Fuel,
+    Memory,
+    Time,
+    Stack,
+}
+
+impl fmt::Display for ResourceExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let resource = match self {
+            ResourceExhausted::Fuel => "fuel",
+            ResourceExhausted::Memory => "memory",
+            ResourceExhausted::Time => "time",
+            ResourceExhausted::Stack => "stack",
+        };
+        write!(f, "resource exhausted: {}", resource)
+    }
+}
+
+impl std::error::Error for ResourceExhausted {}
+
+/// A `test` declaration met while evaluating a program, to be run later
+/// with `Interpreter::run_test`.
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    pub name: String,
+    /// Line of the `test` keyword.
+    pub line: usize,
+    pub(crate) body: Rc<Node>,
+    // the environment the declaration was evaluated in
+    pub(crate) env: Rc<RefCell<Environment>>,
+}
+
+// A call of a function written in the language being evaluated.
+struct Call {
+    // `None` for an anonymous function
+    function: Option<Rc<str>>,
+    line: Option<usize>,
+    // the bindings of the parameters, shared with the environment of the
+    // call and only shown when an error comes out of it; `None` until the
+    // arguments are bound
+    args: Option<Vec<Rc<RefCell<Object>>>>,
+}
+
+// Reading the clock on every step would dominate evaluation time.
+const STEPS_PER_CLOCK_CHECK: u64 = 1024;
+
+/// State shared by everything evaluated through one interpreter. Host
+/// functions receive it so they can call back into the program.
+pub struct Ctx {
+    limits: Limits,
+    fuel: u64,
+    steps: u64,
+    deadline: Option<Instant>,
+    depth: usize,
+    meter: Meter,
+    exhausted: Option<ResourceExhausted>,
+    io_policy: IoPolicy,
+    output: Box<dyn Write>,
+    // `None` reads the process' stdin
+    input: Option<Box<dyn BufRead>>,
+    tests: Vec<TestCase>,
+    // outermost first
+    calls: Vec<Call>,
+    // `None` unless tracing was asked for, so that the evaluator only
+    // pays for a check
+    tracer: Option<Tracer>,
+    debugger: Option<Debugger>,
+}
+
+impl fmt::Debug for Ctx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ctx")
+            .field("limits", &self.limits)
+            .field("io_policy", &self.io_policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Ctx {
+    pub fn new() -> Self {
+        Ctx::with_limits(Limits::default())
+    }
+
+    pub fn with_limits(limits: Limits) -> Self {
+        let mut ctx = Ctx {
+            limits,
+            fuel: 0,
+            steps: 0,
+            deadline: None,
+            depth: 0,
+            meter: Meter::default(),
+            exhausted: None,
+            io_policy: IoPolicy::default(),
+            output: Box::new(io::stdout()),
+            input: None,
+            tests: vec![],
+            calls: vec![],
+            tracer: None,
+            debugger: None,
+        };
+        ctx.start();
+        ctx
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+        self.start();
+    }
+
+    /// The I/O the builtin functions may do.
+    pub fn io_policy(&self) -> &IoPolicy {
+        &self.io_policy
+    }
+
+    pub fn set_io_policy(&mut self, policy: IoPolicy) {
+        self.io_policy = policy;
+    }
+
+    /// Where `puts` and `print` write to, stdout by default.
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    /// Where `input` reads from, stdin by default.
+    pub fn set_input(&mut self, input: Box<dyn BufRead>) {
+        self.input = Some(input);
+    }
+
+    /// Writes a trace of the evaluation to `output`; see `Tracer`.
+    pub fn set_trace(&mut self, output: Box<dyn Write>) {
+        self.tracer = Some(Tracer::new(output));
+    }
+
+    pub(crate) fn tracer(&mut self) -> Option<&mut Tracer> {
+        self.tracer.as_mut()
+    }
+
+    /// Lets `debugger` pause the evaluation before statements.
+    pub fn set_debugger(&mut self, debugger: Debugger) {
+        self.debugger = Some(debugger);
+    }
+
+    pub(crate) fn debugger(&mut self) -> Option<&mut Debugger> {
+        self.debugger.as_mut()
+    }
+
+    // Whether calls have to be reported to a tracer or a debugger.
+    pub(crate) fn hooked(&self) -> bool {
+        self.tracer.is_some() || self.debugger.is_some()
+    }
+
+    pub fn output(&mut self) -> &mut dyn Write {
+        self.output.as_mut()
+    }
+
+    /// Gives back the output set with `set_output`, leaving stdout in its
+    /// place.
+    pub(crate) fn take_output(&mut self) -> Box<dyn Write> {
+        std::mem::replace(&mut self.output, Box::new(io::stdout()))
+    }
+
+    /// Reads a line including its line break into `buf`, returning the
+    /// number of bytes read, which is 0 at the end of the input.
+    pub fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        match &mut self.input {
+            Some(input) => input.read_line(buf),
+            None => io::stdin().read_line(buf),
+        }
+    }
+
+    /// The test cases declared since the last call, in order.
+    pub fn take_tests(&mut self) -> Vec<TestCase> {
+        std::mem::take(&mut self.tests)
+    }
+
+    pub(crate) fn declare_test(&mut self, test: TestCase) {
+        self.tests.push(test);
+    }
+
+    /// Counts `env` and every environment created from it towards the
+    /// memory limit.
+    pub fn track(&self, env: &Rc<RefCell<Environment>>) {
+        env.borrow_mut().set_meter(Rc::clone(&self.meter));
+    }
+
+    /// Refills the fuel and restarts the clock before an evaluation.
+    pub fn start(&mut self) {
+        self.fuel = self.limits.fuel.unwrap_or(u64::MAX);
+        self.steps = 0;
+        self.deadline = self.limits.timeout.map(|timeout| Instant::now() + timeout);
+        self.depth = 0;
+        self.calls.clear();
+        self.exhausted = None;
+    }
+
+    /// Evaluation steps taken since the last `start`.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// The limit the last evaluation ran into, if any.
+    pub fn take_exhausted(&mut self) -> Option<ResourceExhausted> {
+        self.exhausted.take()
+    }
+
+    /// Charges one evaluation step.
+    pub(crate) fn step(&mut self) -> Result<(), RuntimeError> {
+        if self.fuel == 0 {
+            return self.exhaust(ResourceExhausted::Fuel);
+        }
+        self.fuel -= 1;
+
+        self.steps += 1;
+        if let Some(deadline) = self.deadline {
+            if self.steps.is_multiple_of(STEPS_PER_CLOCK_CHECK) && Instant::now() >= deadline {
+                return self.exhaust(ResourceExhausted::Time);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_memory(&mut self) -> Result<(), RuntimeError> {
+        match self.limits.memory {
+            Some(limit) if self.meter.get() > limit => self.exhaust(ResourceExhausted::Memory),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that a value of `size` can be made without going over the
+    /// memory limit, before making it.
+    pub(crate) fn check_alloc(&mut self, size: usize) -> Result<(), RuntimeError> {
+        match self.limits.memory {
+            Some(limit) if self.meter.get().saturating_add(size) > limit => {
+                self.exhaust(ResourceExhausted::Memory)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn enter_call(&mut self) -> Result<(), RuntimeError> {
+        if self.limits.max_depth.is_some_and(|max| self.depth >= max) {
+            return self.exhaust(ResourceExhausted::Stack);
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    pub(crate) fn exit_call(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Keeps a frame for a call of `function` on `line`, if it is written
+    /// in the language, and tells whether it did. `pop_frame` must follow
+    /// when it did.
+    pub(crate) fn push_frame(&mut self, function: &Object, line: Option<usize>) -> bool {
+        let Object::Function(f) = function else {
+            return false;
+        };
+        self.calls.push(Call {
+            function: f.name.clone(),
+            line,
+            args: None,
+        });
+        true
+    }
+
+    /// Whether the innermost frame waits for the bindings of its
+    /// arguments. Functions called back by the host have no frame of
+    /// their own, and find the frame of their caller filled in already.
+    pub(crate) fn wants_args(&self) -> bool {
+        self.calls.last().is_some_and(|call| call.args.is_none())
+    }
+
+    pub(crate) fn set_args(&mut self, args: Vec<Rc<RefCell<Object>>>) {
+        if let Some(call) = self.calls.last_mut() {
+            call.args = Some(args);
+        }
+    }
+
+    /// Drops the innermost frame. An error coming out of the call gets
+    /// the frames still open as its backtrace, unless an inner call
+    /// already gave it one.
+    pub(crate) fn pop_frame(&mut self, result: &mut Option<Object>) {
+        if let Some(Object::Error(err)) = result {
+            if err.backtrace.is_empty() {
+                err.backtrace = self
+                    .calls
+                    .iter()
+                    .rev()
+                    .map(|call| Frame {
+                        function: call
+                            .function
+                            .as_deref()
+                            .unwrap_or("<anonymous>")
+                            .to_string(),
+                        line: call.line,
+                        args: call
+                            .args
+                            .iter()
+                            .flatten()
+                            .map(|arg| arg.borrow().inspect())
+                            .collect(),
+                    })
+                    .collect();
+            }
+        }
+        self.calls.pop();
+    }
+
+    fn exhaust(&mut self, resource: ResourceExhausted) -> Result<(), RuntimeError> {
+        self.exhausted = Some(resource);
+        Err(RuntimeError::new(resource.to_string()))
+    }
+
+    /// Calls `function`, a function value written in the language or
+    /// registered by the host, with `args`.
+    pub fn call(&mut self, function: &Object, args: Vec<Object>) -> Result<Object, RuntimeError> {
+        let result = if self.hooked() {
+            let name = match function {
+                Object::Builtin(f) => f.name.as_str(),
+                _ => "<callback>",
+            };
+            apply_hooked(name, None, function.clone(), args, self)
+        } else {
+            apply_function(function.clone(), args, self)
+        };
+        match result {
+            Some(Object::Error(err)) => Err(err),
+            Some(Object::Return(v)) => Ok(v.value.as_ref().clone()),
+            Some(value) => Ok(value),
+            None => Ok(Object::Null),
+        }
+    }
+}
+
+impl Default for Ctx {
+    fn default() -> Self {
+        Ctx::new()
+    }
+}
@@ -0,0 +1,385 @@
+use super::{
+    context::{Ctx, Limits},
+    environment::Environment,
+    eval_with,
+    io::IoPolicy,
+    object::Object,
+    resolver::Resolver,
+};
+use crate::{
+    lexer::Lexer,
+    parser::{ast::Node, Parser},
+};
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    fmt,
+    io::{self, BufRead, Write},
+    rc::Rc,
+};
+
+// Limits of an expression evaluated by `print`, so that a mistyped loop or
+// recursion does not hang or overflow the paused program. Its calls are
+// nested on top of the program's, on whatever stack is left, so only a
+// few are allowed.
+const PRINT_FUEL: u64 = 1_000_000;
+const PRINT_MAX_DEPTH: usize = 100;
+
+const HELP: &str = "commands:
+    break <line>   pause before the statements on <line>   (b)
+    step           run to the next statement               (s)
+    next           run to the next statement in this call  (n)
+    finish         run until this call returns             (f)
+    continue       run to the next breakpoint              (c)
+    print <expr>   evaluate <expr> where the program is    (p)
+    locals         list the bindings of this scope
+    backtrace      list the calls being evaluated          (bt)
+    help           print this message                      (h)";
+
+// A call being evaluated.
+struct Frame {
+    name: String,
+    // where the call is, in the caller
+    line: Option<usize>,
+    args: Vec<String>,
+}
+
+// Where to pause next, besides the breakpoints. The depths are numbers
+// of calls being evaluated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Step,
+    Next(usize),
+    Finish(usize),
+    Continue,
+}
+
+/// Pauses a program before its statements and reads commands telling it
+/// what to show and where to pause next, like `gdb` does. It starts out
+/// paused before the first statement. At the end of its input it lets
+/// the program run to completion.
+pub struct Debugger {
+    source: Vec<String>,
+    // `None` reads the process' stdin
+    input: Option<Box<dyn BufRead>>,
+    output: Box<dyn Write>,
+    breakpoints: BTreeSet<usize>,
+    frames: Vec<Frame>,
+    mode: Mode,
+}
+
+impl fmt::Debug for Debugger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Debugger")
+            .field("breakpoints", &self.breakpoints)
+            .field("mode", &self.mode)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Debugger {
+    /// Creates a debugger for the program `source`, which is shown when
+    /// it pauses, talking over stdin and stdout.
+    pub fn new(source: &str) -> Self {
+        Debugger {
+            source: source.lines().map(String::from).collect(),
+            input: None,
+            output: Box::new(io::stdout()),
+            breakpoints: BTreeSet::new(),
+            frames: vec![],
+            mode: Mode::Step,
+        }
+    }
+
+    /// Reads commands from `input` instead of stdin.
+    pub fn with_input<R: BufRead + 'static>(mut self, input: R) -> Self {
+        self.input = Some(Box::new(input));
+        self
+    }
+
+    /// Writes to `output` instead of stdout.
+    pub fn with_output<W: Write + 'static>(mut self, output: W) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    pub(crate) fn call(&mut self, name: &str, line: Option<usize>, args: &[Object]) {
+        self.frames.push(Frame {
+            name: name.to_string(),
+            line,
+            args: args.iter().map(Object::inspect).collect(),
+        });
+    }
+
+    pub(crate) fn ret(&mut self) {
+        self.frames.pop();
+    }
+
+    /// Pauses before `stmt`, evaluated in `env`, if a breakpoint or the
+    /// last command says so. Statements without a line never pause.
+    pub(crate) fn before(&mut self, stmt: &Node, env: &Rc<RefCell<Environment>>) {
+        let Some(line) = stmt.line() else {
+            return;
+        };
+        let depth = self.frames.len();
+        let pause = match self.mode {
+            Mode::Step => true,
+            Mode::Next(at) => depth <= at,
+            Mode::Finish(at) => depth < at,
+            Mode::Continue => false,
+        };
+        if pause || self.breakpoints.contains(&line) {
+            self.pause(line, env);
+        }
+    }
+
+    // Reads commands until one resumes the program.
+    fn pause(&mut self, line: usize, env: &Rc<RefCell<Environment>>) {
+        self.show_line(line);
+        loop {
+            let _ = write!(self.output, "(debug) ");
+            let _ = self.output.flush();
+            let mut command = String::new();
+            let read = match &mut self.input {
+                Some(input) => input.read_line(&mut command),
+                None => io::stdin().read_line(&mut command),
+            };
+            if !matches!(read, Ok(n) if n > 0) {
+                let _ = writeln!(self.output);
+                self.breakpoints.clear();
+                self.mode = Mode::Continue;
+                return;
+            }
+
+            let command = command.trim();
+            let (name, arg) = command
+                .split_once(char::is_whitespace)
+                .unwrap_or((command, ""));
+            let arg = arg.trim();
+            let depth = self.frames.len();
+            match name {
+                "" => {}
+                "break" | "b" => match arg.parse::<usize>() {
+                    Ok(line) if line > 0 => {
+                        self.breakpoints.insert(line);
+                        self.say(format_args!("breakpoint at line {}", line));
+                    }
+                    _ => self.say(format_args!("usage: break <line>")),
+                },
+                "step" | "s" => return self.resume(Mode::Step),
+                "next" | "n" => return self.resume(Mode::Next(depth)),
+                "finish" | "f" if depth == 0 => self.say(format_args!("not in a function")),
+                "finish" | "f" => return self.resume(Mode::Finish(depth)),
+                "continue" | "c" => return self.resume(Mode::Continue),
+                "print" | "p" if arg.is_empty() => self.say(format_args!("usage: print <expr>")),
+                "print" | "p" => self.print(arg, env),
+                "locals" => self.locals(&env.borrow()),
+                "backtrace" | "bt" => self.backtrace(line),
+                "help" | "h" => self.say(format_args!("{}", HELP)),
+                _ => self.say(format_args!(
+                    "unknown command: {}; type help for the commands",
+                    name
+                )),
+            }
+        }
+    }
+
+    fn resume(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    fn show_line(&mut self, line: usize) {
+        let text = self.source.get(line - 1).map_or("", |text| text.trim());
+        let _ = writeln!(self.output, "line {}: {}", line, text);
+    }
+
+    // Evaluates `input` in a scope of its own inside the paused
+    // environment, so that it binds nothing in the program, with a context
+    // of its own so that it neither pauses nor uses up the program's
+    // limits. It writes to the debugger's output and may do no I/O.
+    fn print(&mut self, input: &str, env: &Rc<RefCell<Environment>>) {
+        let mut parser = Parser::new(Lexer::new(input));
+        let mut prog = parser.parse_program();
+        if !parser.errors.is_empty() {
+            self.say(format_args!("{}", parser.errors.join("\n")));
+            return;
+        }
+        let env = Environment::new_extended(Rc::clone(env));
+        let mut resolver = Resolver::new(&env);
+        resolver.resolve_program(&mut prog);
+        if !resolver.errors.is_empty() {
+            self.say(format_args!("{}", resolver.errors.join("\n")));
+            return;
+        }
+
+        let mut ctx = Ctx::with_limits(Limits {
+            fuel: Some(PRINT_FUEL),
+            max_depth: Some(PRINT_MAX_DEPTH),
+            ..Limits::default()
+        });
+        ctx.set_io_policy(IoPolicy::deny_all());
+        ctx.set_output(std::mem::replace(&mut self.output, Box::new(io::sink())));
+        let result = eval_with(&prog, env, &mut ctx);
+        self.output = ctx.take_output();
+        match result {
+            Some(Object::Error(err)) => self.say(format_args!("error: {}", err)),
+            Some(value) => self.say(format_args!("{}", value.inspect())),
+            None => self.say(format_args!("null")),
+        }
+    }
+
+    // The builtins bound in the global scope are left out.
+    fn locals(&mut self, env: &Environment) {
+        let mut names = env.declared();
+        names.sort();
+        let mut listed = vec![];
+        for (name, idx) in names {
+            let Some(Some(value)) = env.slots.get(idx) else {
+                continue;
+            };
+            let value = value.borrow();
+            if !matches!(*value, Object::Builtin(_)) {
+                listed.push(format!("{} = {}", name, value.inspect()));
+            }
+        }
+        if listed.is_empty() {
+            self.say(format_args!("no locals"));
+        } else {
+            self.say(format_args!("{}", listed.join("\n")));
+        }
+    }
+
+    // Innermost call first, each with the line it is at.
+    fn backtrace(&mut self, line: usize) {
+        let mut lines = vec![];
+        let mut at = Some(line);
+        for (i, frame) in self.frames.iter().rev().enumerate() {
+            lines.push(format!(
+                "#{} {}({}) at line {}",
+                i,
+                frame.name,
+                frame.args.join(", "),
+                at.map_or(String::from("?"), |line| line.to_string())
+            ));
+            at = frame.line;
+        }
+        lines.push(format!(
+            "#{} <program> at line {}",
+            self.frames.len(),
+            at.map_or(String::from("?"), |line| line.to_string())
+        ));
+        self.say(format_args!("{}", lines.join("\n")));
+    }
+
+    // Output to the user is best effort, like the trace.
+    fn say(&mut self, text: fmt::Arguments) {
+        let _ = writeln!(self.output, "{}", text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Interpreter, OutputBuffer};
+
+    const PROGRAM: &str = "let add = fn(a, b) {
+  let sum = a + b;
+  sum
+};
+let twice = fn(x) { add(x, x) };
+let y = twice(3);
+let z = y + 1;";
+
+    fn session(commands: &str) -> String {
+        let output = OutputBuffer::new();
+        let debugger = Debugger::new(PROGRAM)
+            .with_input(io::Cursor::new(commands.to_string()))
+            .with_output(output.clone());
+        let mut interpreter = Interpreter::new().with_debugger(debugger);
+        interpreter.eval(PROGRAM).unwrap();
+        output.contents()
+    }
+
+    #[test]
+    fn test_breakpoints_and_inspection() {
+        let commands = "break 2\ncontinue\nlocals\nprint a * 10\nbacktrace\ncontinue\n";
+        let expected = [
+            "line 1: let add = fn(a, b) {",
+            "(debug) breakpoint at line 2",
+            "(debug) line 2: let sum = a + b;",
+            "(debug) a = 3",
+            "b = 3",
+            "(debug) 30",
+            "(debug) #0 add(3, 3) at line 2",
+            "#1 twice(3) at line 5",
+            "#2 <program> at line 6",
+            "(debug) ",
+        ];
+        assert_eq!(session(commands), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_stepping() {
+        let commands = "next\nnext\nstep\nstep\nstep\nfinish\nnext\n";
+        let expected = [
+            "line 1: let add = fn(a, b) {",
+            "(debug) line 5: let twice = fn(x) { add(x, x) };",
+            "(debug) line 6: let y = twice(3);",
+            "(debug) line 5: let twice = fn(x) { add(x, x) };",
+            "(debug) line 2: let sum = a + b;",
+            "(debug) line 3: sum",
+            "(debug) line 7: let z = y + 1;",
+            "(debug) ",
+        ];
+        assert_eq!(session(commands), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_end_of_input_runs_to_completion() {
+        let output = session("break 7\nfinish\nbogus\n");
+        assert!(output.contains("(debug) not in a function\n"), "{}", output);
+        assert!(
+            output.contains("(debug) unknown command: bogus; type help for the commands\n"),
+            "{}",
+            output
+        );
+        assert!(!output.contains("line 7: "), "{}", output);
+    }
+
+    #[test]
+    fn test_print_is_confined() {
+        let commands =
+            "break 3\ncontinue\nprint let t = sum;\nlocals\nprint puts(sum)\nprint later\n";
+        let expected = [
+            "line 1: let add = fn(a, b) {",
+            "(debug) breakpoint at line 3",
+            "(debug) line 3: sum",
+            "(debug) null",
+            "(debug) a = 3",
+            "b = 3",
+            "sum = 6",
+            "(debug) error: permission denied: stdout",
+            "(debug) line 1: identifier not found: later",
+            "(debug) ",
+            "",
+        ];
+        assert_eq!(session(commands), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_print_is_limited() {
+        let commands = "break 3\ncontinue\nprint while (true) {}\n\
+            print let f = fn(n) { f(n + 1) }; f(0);\nprint sum\n";
+        let expected = [
+            "line 1: let add = fn(a, b) {",
+            "(debug) breakpoint at line 3",
+            "(debug) line 3: sum",
+            "(debug) error: resource exhausted: fuel",
+            "(debug) error: resource exhausted: stack",
+            "(debug) 6",
+            "(debug) ",
+            "",
+        ];
+        assert_eq!(session(commands), expected.join("\n"));
+    }
+}
@@ -1,10 +1,32 @@
 use super::object::*;
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use crate::parser::ast::Slot;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
+
+/// Shared count of the environments, bindings and bound values alive in
+/// an environment tree, used to enforce a memory limit. See `Object::size`.
+pub type Meter = Rc<Cell<usize>>;
 
 #[derive(Debug)]
 pub struct Environment {
-    pub store: HashMap<String, Rc<RefCell<Object>>>,
+    /// Maps a binding name to its index in `slots`, for the bindings the
+    /// resolver did not name in `names`.
+    pub store: HashMap<String, usize>,
+    /// The bindings, `None` for slots skipped over by the resolver that
+    /// were never bound (e.g. a `let` inside an untaken branch).
+    pub slots: Vec<Option<Rc<RefCell<Object>>>>,
     pub outer: Option<Rc<RefCell<Environment>>>,
+    // the name of each slot of a function call, shared by every call of
+    // the function so that binding arguments does not build a map
+    names: Option<Rc<Vec<String>>>,
+    // sum of the sizes of the values in `slots` when they were bound
+    values: usize,
+    // inherited from `outer`, so every environment created below a
+    // metered one is counted as well
+    meter: Option<Meter>,
 }
 
 impl Environment {
@@ -14,33 +36,158 @@ impl Environment {
     pub fn new() -> Rc<RefCell<Environment>> {
         Rc::new(RefCell::new(Environment {
             store: HashMap::new(),
+            slots: vec![],
             outer: None,
+            names: None,
+            values: 0,
+            meter: None,
         }))
     }
 
     /// Creates an empty `Environment` that extends and existing environment.
     pub fn new_extended(outer: Rc<RefCell<Environment>>) -> Rc<RefCell<Environment>> {
+        Environment::extend(outer, None)
+    }
+
+    /// Creates an empty `Environment` for a call, whose slots are named by
+    /// `names` as the resolver laid them out.
+    pub fn new_call(
+        outer: Rc<RefCell<Environment>>,
+        names: Rc<Vec<String>>,
+    ) -> Rc<RefCell<Environment>> {
+        Environment::extend(outer, Some(names))
+    }
+
+    fn extend(
+        outer: Rc<RefCell<Environment>>,
+        names: Option<Rc<Vec<String>>>,
+    ) -> Rc<RefCell<Environment>> {
+        let meter = outer.borrow().meter.clone();
+        if let Some(meter) = &meter {
+            meter.set(meter.get() + 1);
+        }
         Rc::new(RefCell::new(Environment {
             store: HashMap::new(),
+            slots: vec![],
             outer: Some(outer),
+            names,
+            values: 0,
+            meter,
         }))
     }
 
+    /// Starts counting this environment, its bindings and all
+    /// environments later created from it in `meter`.
+    pub fn set_meter(&mut self, meter: Meter) {
+        meter.set(meter.get() + self.size());
+        self.meter = Some(meter);
+    }
+
+    // the environment itself plus one for each binding and its value
+    fn size(&self) -> usize {
+        1 + self.slots.len() + self.values
+    }
+
     /// Returns a refernce to an `Object`` corresponding to a key.
     /// If key is not defined in the current environment, it will
     /// then recursively call `self.outer.get`, and attempt  to
     /// retrieve the value from its outer environment.
-    pub fn get(&self, k: String) -> Option<Rc<RefCell<Object>>> {
-        self.store.get(&k).map_or_else(
-            || match &self.outer.as_ref() {
-                Some(v) => Some(Rc::clone(*v).as_ref().borrow().get(k.clone()).unwrap()),
-                None => None,
-            },
-            |v| Some(Rc::clone(v)),
-        )
+    pub fn get(&self, k: &str) -> Option<Rc<RefCell<Object>>> {
+        match self.index_of(k).and_then(|idx| self.slots.get(idx)) {
+            Some(Some(value)) => Some(Rc::clone(value)),
+            _ => self.outer.as_ref()?.borrow().get(k),
+        }
+    }
+
+    fn index_of(&self, k: &str) -> Option<usize> {
+        if let Some(idx) = self.store.get(k) {
+            return Some(*idx);
+        }
+        self.names.as_ref()?.iter().position(|name| name == k)
+    }
+
+    /// The names of this environment with the indices of their slots,
+    /// whether or not they are bound yet.
+    pub fn declared(&self) -> Vec<(&str, usize)> {
+        let named = self.names.iter().flat_map(|names| names.iter().enumerate());
+        named
+            .map(|(idx, name)| (name.as_str(), idx))
+            .chain(self.store.iter().map(|(name, idx)| (name.as_str(), *idx)))
+            .collect()
+    }
+
+    /// Names bound in this environment and the ones it extends, sorted
+    /// and without duplicates.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .declared()
+            .into_iter()
+            .filter(|(_, idx)| matches!(self.slots.get(*idx), Some(Some(_))))
+            .map(|(name, _)| name.to_string())
+            .collect();
+        if let Some(outer) = &self.outer {
+            names.extend(outer.borrow().names());
+        }
+        names.sort();
+        names.dedup();
+        names
     }
 
+    /// Returns the `Object` stored at a slot computed by the resolver,
+    /// walking `slot.depth` environments outwards without hashing.
+    pub fn get_slot(&self, slot: Slot) -> Option<Rc<RefCell<Object>>> {
+        if slot.depth == 0 {
+            return self.slots.get(slot.index)?.as_ref().map(Rc::clone);
+        }
+        let outer = Slot {
+            depth: slot.depth - 1,
+            index: slot.index,
+        };
+        self.outer.as_ref()?.borrow().get_slot(outer)
+    }
+
+    /// Binds `k` in the current environment, reusing its slot when the name
+    /// is already bound and appending a new slot otherwise.
     pub fn insert(&mut self, k: String, v: Object) -> Option<Rc<RefCell<Object>>> {
-        self.store.insert(k, Rc::new(RefCell::new(v)))
+        if let Some(idx) = self.index_of(&k) {
+            return self.bind(idx, v);
+        }
+        let idx = self.slots.len();
+        self.store.insert(k, idx);
+        self.bind(idx, v)
+    }
+
+    /// Binds `k` to the slot `idx` assigned by the resolver. Slots that are
+    /// skipped over (e.g. a `let` inside an untaken branch) stay unbound.
+    /// The name is only recorded when the slots are not named already.
+    pub fn define(&mut self, idx: usize, k: &str, v: Object) -> Option<Rc<RefCell<Object>>> {
+        if self.names.is_none() && !self.store.contains_key(k) {
+            self.store.insert(k.to_string(), idx);
+        }
+        self.bind(idx, v)
+    }
+
+    fn bind(&mut self, idx: usize, v: Object) -> Option<Rc<RefCell<Object>>> {
+        if self.slots.len() <= idx {
+            if let Some(meter) = &self.meter {
+                meter.set(meter.get() + idx + 1 - self.slots.len());
+            }
+            self.slots.resize_with(idx + 1, || None);
+        }
+        let freed = self.slots[idx].as_ref().map_or(0, |v| v.borrow().size());
+        let taken = v.size();
+        if let Some(meter) = &self.meter {
+            meter.set((meter.get() + taken).saturating_sub(freed));
+        }
+        self.values = (self.values + taken).saturating_sub(freed);
+        self.slots[idx].replace(Rc::new(RefCell::new(v)))
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        if let Some(meter) = &self.meter {
+            meter.set(meter.get().saturating_sub(self.size()));
+        }
     }
 }
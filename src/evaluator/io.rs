@@ -0,0 +1,133 @@
+use super::object::RuntimeError;
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+/// The I/O a program is allowed to do through the builtin functions.
+/// Everything is denied by default, so a host only hands out the
+/// capabilities it means to.
+#[derive(Debug, Clone, Default)]
+pub struct IoPolicy {
+    /// `puts` and `print`.
+    pub stdout: bool,
+    /// `input`.
+    pub stdin: bool,
+    /// `read_file`.
+    pub fs_read: bool,
+    /// `write_file`.
+    pub fs_write: bool,
+    /// When set, files can only be read and written below this directory
+    /// and relative paths are taken relative to it.
+    pub fs_root: Option<PathBuf>,
+    /// `env`.
+    pub env: bool,
+}
+
+impl IoPolicy {
+    pub fn deny_all() -> Self {
+        IoPolicy::default()
+    }
+
+    pub fn allow_all() -> Self {
+        IoPolicy {
+            stdout: true,
+            stdin: true,
+            fs_read: true,
+            fs_write: true,
+            fs_root: None,
+            env: true,
+        }
+    }
+
+    pub(crate) fn check(allowed: bool, what: &str) -> Result<(), RuntimeError> {
+        if allowed {
+            Ok(())
+        } else {
+            Err(permission_denied(what))
+        }
+    }
+
+    /// Resolves `path` for reading, or fails when reading it is not
+    /// allowed.
+    pub(crate) fn readable(&self, path: &str) -> Result<PathBuf, RuntimeError> {
+        IoPolicy::check(self.fs_read, &format!("read {}", path))?;
+        self.confine(path)
+            .ok_or_else(|| permission_denied(&format!("read {}", path)))
+    }
+
+    /// Resolves `path` for writing, or fails when writing it is not
+    /// allowed.
+    pub(crate) fn writable(&self, path: &str) -> Result<PathBuf, RuntimeError> {
+        IoPolicy::check(self.fs_write, &format!("write {}", path))?;
+        self.confine(path)
+            .ok_or_else(|| permission_denied(&format!("write {}", path)))
+    }
+
+    // Paths are compared after resolving `..` and symbolic links, so a
+    // path can't climb out of the root. The file itself may not exist
+    // yet when writing, but its directory must. Something that exists
+    // but can't be resolved is a dangling (or looping) symbolic link,
+    // which could point anywhere once written through, so it is refused.
+    fn confine(&self, path: &str) -> Option<PathBuf> {
+        let Some(root) = &self.fs_root else {
+            return Some(PathBuf::from(path));
+        };
+        let root = root.canonicalize().ok()?;
+        let path = root.join(path);
+
+        let resolved = match path.canonicalize() {
+            Ok(resolved) => resolved,
+            Err(_) => {
+                if path.symlink_metadata().is_ok() {
+                    return None;
+                }
+                let parent = path.parent()?.canonicalize().ok()?;
+                parent.join(path.file_name()?)
+            }
+        };
+        resolved.starts_with(&root).then_some(resolved)
+    }
+}
+
+fn permission_denied(what: &str) -> RuntimeError {
+    RuntimeError::new(format!("permission denied: {}", what))
+}
+
+pub(crate) fn io_error(path: &Path, err: io::Error) -> RuntimeError {
+    RuntimeError::new(format!("{}: {}", path.display(), err))
+}
+
+/// An output sink that keeps everything written to it, for capturing
+/// what a program prints. Clones share the same buffer, so a host can
+/// hand one clone to the interpreter and read the output from another.
+#[derive(Debug, Clone, Default)]
+pub struct OutputBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl OutputBuffer {
+    pub fn new() -> Self {
+        OutputBuffer::default()
+    }
+
+    /// Everything written so far, with invalid UTF-8 replaced.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,61 +1,156 @@
+pub mod context;
+pub mod debugger;
 pub mod environment;
+pub mod io;
 pub mod object;
+pub mod resolver;
+pub mod trace;
 
 use crate::parser::ast::*;
+use context::{Ctx, TestCase};
 use environment::*;
 use object::*;
 use std::{borrow::Borrow, cell::RefCell, rc::Rc};
 
 pub fn eval(node: &Node, env: Rc<RefCell<Environment>>) -> Option<Object> {
+    eval_with(node, env, &mut Ctx::new())
+}
+
+/// Evaluates `node` with `ctx` as the state shared with host functions.
+pub fn eval_with(node: &Node, env: Rc<RefCell<Environment>>, ctx: &mut Ctx) -> Option<Object> {
+    if let Err(err) = ctx.step() {
+        return Some(Object::Error(err));
+    }
+    if let Some(tracer) = ctx.tracer() {
+        tracer.enter(node);
+    }
     let env = Rc::clone(&env);
-    match node {
-        Node::Program(p) => eval_statements(&p.statements, env),
-        Node::BlockStatement(p) => eval_statements(&p.statements, env),
-        Node::CallExpression(v) => eval_call(v, env),
-        Node::LetStatement(v) => eval_let_statement(v, env),
-        Node::ReturnStatement(v) => eval_return_statement(v, env),
-        Node::IfExpression(v) => eval_if_expression(v, env),
-        Node::Prefix(v) => eval_prefix(v, env),
-        Node::Infix(v) => eval_infix(v, env),
-        Node::Function(v) => eval_function_literal(v, env),
+    let mut result = match node {
+        Node::Program(p) => eval_statements(&p.statements, env, ctx),
+        Node::BlockStatement(p) => eval_statements(&p.statements, env, ctx),
+        Node::CallExpression(v) => eval_call(v, env, ctx),
+        Node::Member(v) => eval_member(v, env, ctx),
+        Node::LetStatement(v) => eval_let_statement(v, env, ctx),
+        Node::ReturnStatement(v) => eval_return_statement(v, env, ctx),
+        Node::IfExpression(v) => eval_if_expression(v, env, ctx),
+        Node::While(v) => eval_while_expression(v, env, ctx),
+        Node::Test(v) => {
+            ctx.declare_test(TestCase {
+                name: v.name.clone(),
+                line: v.line,
+                body: Rc::clone(&v.body),
+                env,
+            });
+            Some(Object::Null)
+        }
+        Node::Prefix(v) => eval_prefix(v, env, ctx),
+        Node::Infix(v) => eval_infix(v, env, ctx),
+        Node::Function(v) => eval_function_literal(v, env, ctx),
         Node::Int(v) => Some(Object::Integer(IntegerObject { value: v.0 })),
-        Node::Ident(v) => eval_identifier(v, env),
+        Node::Ident(v) => eval_identifier(v, env, ctx),
         Node::Boolean(v) => Some(Object::Boolean(to_native_bool(v.0))),
+        Node::Str(v) => Some(Object::String(StringObject { value: v.0.clone() })),
+        Node::Null => Some(Object::Null),
         _ => None,
+    };
+
+    // an error is reported at the innermost node with a line it passes
+    if let Some(Object::Error(err)) = &mut result {
+        if err.line.is_none() {
+            err.line = node.line();
+        }
+    }
+    if let Some(tracer) = ctx.tracer() {
+        tracer.exit(node);
     }
+    result
 }
 
-fn eval_statements(statements: &[Node], env: Rc<RefCell<Environment>>) -> Option<Object> {
-    let mut result = None;
+// A sequence of statements evaluates to its last statement, or to `null`
+// when it is empty.
+fn eval_statements(
+    statements: &[Node],
+    env: Rc<RefCell<Environment>>,
+    ctx: &mut Ctx,
+) -> Option<Object> {
+    let mut result = Some(Object::Null);
     for stmt in statements {
-        result = eval(stmt, Rc::clone(&env));
+        if let Node::Comment(_) = stmt {
+            continue;
+        }
+        if let Some(debugger) = ctx.debugger() {
+            debugger.before(stmt, &env);
+        }
+        result = eval_with(stmt, Rc::clone(&env), ctx);
 
-        if let Some(Object::Return(v)) = result {
-            return Some(v.value.as_ref().clone());
+        match result {
+            Some(Object::Return(v)) => return Some(v.value.as_ref().clone()),
+            Some(Object::Error(_)) => return result,
+            _ => {}
         }
     }
     result
 }
 
-fn eval_block_statements(statements: &[Node], env: Rc<RefCell<Environment>>) -> Option<Object> {
-    let mut result = None;
+fn eval_block_statements(
+    statements: &[Node],
+    env: Rc<RefCell<Environment>>,
+    ctx: &mut Ctx,
+) -> Option<Object> {
+    let mut result = Some(Object::Null);
     for stmt in statements {
-        result = eval(stmt, Rc::clone(&env));
+        if let Node::Comment(_) = stmt {
+            continue;
+        }
+        if let Some(debugger) = ctx.debugger() {
+            debugger.before(stmt, &env);
+        }
+        result = eval_with(stmt, Rc::clone(&env), ctx);
 
-        if let Some(Object::Return(v)) = result {
-            return Some(Object::Return(v));
+        if let Some(Object::Return(_) | Object::Error(_)) = result {
+            return result;
         }
     }
     result
 }
 
-fn eval_if_expression(stmt: &IfExpression, env: Rc<RefCell<Environment>>) -> Option<Object> {
-    let condition = eval(stmt.condition.as_ref(), Rc::clone(&env))?;
+fn eval_if_expression(
+    stmt: &IfExpression,
+    env: Rc<RefCell<Environment>>,
+    ctx: &mut Ctx,
+) -> Option<Object> {
+    let condition = eval_with(stmt.condition.as_ref(), Rc::clone(&env), ctx)?;
+    if condition.is_error() {
+        return Some(condition);
+    }
 
     if is_truthy(&condition) {
-        return eval_block_statements(&stmt.consequence, Rc::clone(&env));
+        return eval_block_statements(&stmt.consequence, Rc::clone(&env), ctx);
     } else {
-        return eval_block_statements(&stmt.alternative, Rc::clone(&env));
+        return eval_block_statements(&stmt.alternative, Rc::clone(&env), ctx);
+    }
+}
+
+// The body shares the environment of the loop, like the branches of an
+// `if`. A `return` or an error inside the body stops the loop.
+fn eval_while_expression(
+    stmt: &WhileExpression,
+    env: Rc<RefCell<Environment>>,
+    ctx: &mut Ctx,
+) -> Option<Object> {
+    loop {
+        let condition = eval_with(&stmt.condition, Rc::clone(&env), ctx)?;
+        if condition.is_error() {
+            return Some(condition);
+        }
+        if !is_truthy(&condition) {
+            return Some(Object::Null);
+        }
+
+        let result = eval_block_statements(&stmt.body, Rc::clone(&env), ctx);
+        if let Some(Object::Return(_) | Object::Error(_)) = result {
+            return result;
+        }
     }
 }
 
@@ -73,17 +168,177 @@ fn is_truthy(obj: &Object) -> bool {
     }
 }
 
-fn eval_call(call: &CallExpression, env: Rc<RefCell<Environment>>) -> Option<Object> {
-    let func_local_env = Environment::new_extended(Rc::clone(&env));
-    match eval(call.function.borrow(), Rc::clone(&env)).unwrap() {
+fn eval_call(
+    call: &CallExpression,
+    env: Rc<RefCell<Environment>>,
+    ctx: &mut Ctx,
+) -> Option<Object> {
+    if let Node::Member(member) = call.function.as_ref() {
+        return eval_method_call(member, &call.arguments, env, ctx);
+    }
+
+    let function = eval_with(call.function.borrow(), Rc::clone(&env), ctx)?;
+    if function.is_error() {
+        return Some(function);
+    }
+
+    let args = match eval_arguments(&call.arguments, env, ctx) {
+        Ok(args) => args,
+        Err(err) => return Some(err),
+    };
+    let framed = ctx.push_frame(&function, Some(call.line));
+    let mut result = if ctx.hooked() {
+        let name = call.function.to_string();
+        apply_hooked(&name, Some(call.line), function, args, ctx)
+    } else {
+        apply_function(function, args, ctx)
+    };
+    if framed {
+        ctx.pop_frame(&mut result);
+    }
+    result
+}
+
+// Calls `function` like `apply_function`, telling the tracer and the
+// debugger about the call, made on `line` to `name`, and its return.
+fn apply_hooked(
+    name: &str,
+    line: Option<usize>,
+    function: Object,
+    args: Vec<Object>,
+    ctx: &mut Ctx,
+) -> Option<Object> {
+    if let Some(tracer) = ctx.tracer() {
+        tracer.call(name, &args);
+    }
+    if let Some(debugger) = ctx.debugger() {
+        debugger.call(name, line, &args);
+    }
+    let result = apply_function(function, args, ctx);
+    if let Some(debugger) = ctx.debugger() {
+        debugger.ret();
+    }
+    if let Some(tracer) = ctx.tracer() {
+        tracer.ret(name, result.as_ref());
+    }
+    result
+}
+
+fn eval_arguments(
+    arguments: &[Node],
+    env: Rc<RefCell<Environment>>,
+    ctx: &mut Ctx,
+) -> Result<Vec<Object>, Object> {
+    let mut args = vec![];
+    for arg in arguments {
+        let value = eval_with(arg, Rc::clone(&env), ctx).unwrap_or(Object::Null);
+        if value.is_error() {
+            return Err(value);
+        }
+        args.push(value);
+    }
+    Ok(args)
+}
+
+// `obj.name(args)` calls the method `name` of a host object, falling back
+// to calling the value of its property `name`.
+fn eval_method_call(
+    member: &MemberExpression,
+    arguments: &[Node],
+    env: Rc<RefCell<Environment>>,
+    ctx: &mut Ctx,
+) -> Option<Object> {
+    let object = eval_with(&member.object, Rc::clone(&env), ctx)?;
+    if object.is_error() {
+        return Some(object);
+    }
+
+    let args = match eval_arguments(arguments, env, ctx) {
+        Ok(args) => args,
+        Err(err) => return Some(err),
+    };
+
+    if let Object::Host(host) = &object {
+        match host.call_method(ctx, &member.property, &args) {
+            Some(Ok(value)) => return admit(value, ctx),
+            Some(Err(err)) => return Some(Object::Error(err)),
+            None => {}
+        }
+    }
+    match get_property(&object, &member.property) {
+        Object::Error(_) => new_error(format!(
+            "unknown method: {}.{}",
+            object.type_name(),
+            member.property
+        )),
+        function => {
+            let framed = ctx.push_frame(&function, Some(member.line));
+            let mut result = if ctx.hooked() {
+                let name = format!("{}.{}", member.object.to_string(), member.property);
+                apply_hooked(&name, Some(member.line), function, args, ctx)
+            } else {
+                apply_function(function, args, ctx)
+            };
+            if framed {
+                ctx.pop_frame(&mut result);
+            }
+            result
+        }
+    }
+}
+
+fn eval_member(
+    member: &MemberExpression,
+    env: Rc<RefCell<Environment>>,
+    ctx: &mut Ctx,
+) -> Option<Object> {
+    let object = eval_with(&member.object, env, ctx)?;
+    if object.is_error() {
+        return Some(object);
+    }
+    admit(get_property(&object, &member.property), ctx)
+}
+
+// Passes on a value coming from the host unless it does not fit in the
+// memory limit.
+fn admit(value: Object, ctx: &mut Ctx) -> Option<Object> {
+    match ctx.check_alloc(value.size()) {
+        Ok(()) => Some(value),
+        Err(err) => Some(Object::Error(err)),
+    }
+}
+
+fn get_property(object: &Object, name: &str) -> Object {
+    let value = match object {
+        Object::Host(host) => host.get(name),
+        _ => None,
+    };
+    value.unwrap_or_else(|| {
+        Object::Error(RuntimeError::new(format!(
+            "unknown property: {}.{}",
+            object.type_name(),
+            name
+        )))
+    })
+}
+
+pub(crate) fn apply_function(function: Object, args: Vec<Object>, ctx: &mut Ctx) -> Option<Object> {
+    match function {
         Object::Function(f) => {
+            if f.parameters.len() != args.len() {
+                return new_error(format!(
+                    "wrong number of arguments: want={}, got={}",
+                    f.parameters.len(),
+                    args.len()
+                ));
+            }
+
+            let func_local_env = Environment::new_call(Rc::clone(&f.env), Rc::clone(&f.locals));
             // set arguments to parameters
-            for (idx, param) in f.parameters.iter().enumerate() {
+            for (param, value) in f.parameters.iter().zip(args) {
                 match param {
                     Node::Ident(i) => {
-                        let value = eval(&call.arguments[idx], Rc::clone(&env))?;
-                        // func_local_env.borrow_mut().store.insert(i.0.clone(), e);
-                        func_local_env.borrow_mut().insert(i.0.clone(), value);
+                        bind(&func_local_env, i, value, ctx);
                     }
                     _ => {
                         return None;
@@ -91,119 +346,255 @@ fn eval_call(call: &CallExpression, env: Rc<RefCell<Environment>>) -> Option<Obj
                 }
             }
 
+            // a rebinding in the body replaces a binding, so the frame
+            // keeps the arguments as they were passed
+            if ctx.wants_args() {
+                let env = func_local_env.as_ref().borrow();
+                let args = f
+                    .parameters
+                    .iter()
+                    .filter_map(|param| match param {
+                        Node::Ident(i) => match i.slot {
+                            Some(slot) => env.get_slot(slot),
+                            None => env.get(&i.name),
+                        },
+                        _ => None,
+                    })
+                    .collect();
+                ctx.set_args(args);
+            }
+
+            if let Err(err) = ctx.check_memory() {
+                return Some(Object::Error(err));
+            }
+            if let Err(err) = ctx.enter_call() {
+                return Some(Object::Error(err));
+            }
+
             // evaluate function body
-            return eval(&f.body, Rc::clone(&func_local_env));
-            // None
+            let result = eval_with(&f.body, Rc::clone(&func_local_env), ctx);
+            ctx.exit_call();
+            result
         }
-        _ => {
-            return None;
+        Object::Builtin(f) => {
+            if !f.arity.accepts(args.len()) {
+                return new_error(format!(
+                    "wrong number of arguments: want={}, got={}",
+                    f.arity,
+                    args.len()
+                ));
+            }
+
+            match (f.func)(ctx, &args) {
+                Ok(value) => admit(value, ctx),
+                Err(err) => Some(Object::Error(err)),
+            }
         }
+        other => new_error(format!("not a function: {}", other.type_name())),
     }
-
-    None
 }
 
-fn eval_prefix(prefix: &PrefixExpression, env: Rc<RefCell<Environment>>) -> Option<Object> {
+fn eval_prefix(
+    prefix: &PrefixExpression,
+    env: Rc<RefCell<Environment>>,
+    ctx: &mut Ctx,
+) -> Option<Object> {
+    let right = eval_with(&prefix.right, env, ctx)?;
+    if right.is_error() {
+        return Some(right);
+    }
+
     match prefix.op.as_str() {
-        "-" => {
-            let right = eval(&prefix.right, env)?;
-            match right {
-                Object::Integer(mut right) => {
-                    right.value *= -1;
-                    return Some(Object::Integer(right));
-                }
-                _ => {
-                    return None;
-                }
-            }
-            if let Object::Integer(mut right) = right {
-                right.value *= -1;
-                Some(Object::Integer(right))
-            } else {
-                None
-            }
-        }
-        "!" => {
-            let right = eval(&prefix.right, env)?;
-            match right {
-                Object::Boolean(b) => {
-                    return match b.value {
-                        true => Some(Object::Boolean(FALSE.clone())),
-                        false => Some(Object::Boolean(TRUE.clone())),
-                    };
-                }
-                _ => return Some(Object::Boolean(FALSE)),
-            }
-        }
-        _ => None,
+        "-" => match right {
+            Object::Integer(right) => match right.value.checked_neg() {
+                Some(value) => Some(Object::Integer(IntegerObject { value })),
+                None => new_error("integer overflow"),
+            },
+            right => new_error(format!("unknown operator: -{}", right.type_name())),
+        },
+        "!" => match right {
+            Object::Boolean(b) => match b.value {
+                true => Some(Object::Boolean(FALSE.clone())),
+                false => Some(Object::Boolean(TRUE.clone())),
+            },
+            Object::Null => Some(Object::Boolean(TRUE)),
+            _ => Some(Object::Boolean(FALSE)),
+        },
+        op => new_error(format!("unknown operator: {}{}", op, right.type_name())),
     }
 }
 
-fn eval_infix(infix: &InfixExpression, env: Rc<RefCell<Environment>>) -> Option<Object> {
-    let left = eval(&infix.left, Rc::clone(&env))?;
-    let right = eval(&infix.right, Rc::clone(&env))?;
+fn eval_infix(
+    infix: &InfixExpression,
+    env: Rc<RefCell<Environment>>,
+    ctx: &mut Ctx,
+) -> Option<Object> {
+    let left = eval_with(&infix.left, Rc::clone(&env), ctx)?;
+    if left.is_error() {
+        return Some(left);
+    }
+    let right = eval_with(&infix.right, Rc::clone(&env), ctx)?;
+    if right.is_error() {
+        return Some(right);
+    }
 
     match (left, right, infix.op.as_str()) {
         (Object::Integer(left), Object::Integer(right), _) => {
             eval_integer_infix_opr(&infix.op, left, right)
         }
-        (_, _, "==") => None,
-        _ => None,
+        (Object::Boolean(left), Object::Boolean(right), "==") => {
+            Some(Object::Boolean(to_native_bool(left.value == right.value)))
+        }
+        (Object::Boolean(left), Object::Boolean(right), "!=") => {
+            Some(Object::Boolean(to_native_bool(left.value != right.value)))
+        }
+        (Object::String(left), Object::String(right), "+") => {
+            if let Err(err) = ctx.check_alloc(left.value.len() + right.value.len()) {
+                return Some(Object::Error(err));
+            }
+            Some(Object::String(StringObject {
+                value: left.value + &right.value,
+            }))
+        }
+        (Object::String(left), Object::String(right), "==") => {
+            Some(Object::Boolean(to_native_bool(left.value == right.value)))
+        }
+        (Object::String(left), Object::String(right), "!=") => {
+            Some(Object::Boolean(to_native_bool(left.value != right.value)))
+        }
+        (Object::Host(left), Object::Host(right), "==") => {
+            Some(Object::Boolean(to_native_bool(host_equals(&left, &right))))
+        }
+        (Object::Host(left), Object::Host(right), "!=") => {
+            Some(Object::Boolean(to_native_bool(!host_equals(&left, &right))))
+        }
+        (Object::Null, Object::Null, "==") => Some(Object::Boolean(TRUE)),
+        (Object::Null, Object::Null, "!=") => Some(Object::Boolean(FALSE)),
+        (left, right, op) if left.type_name() != right.type_name() => new_error(format!(
+            "type mismatch: {} {} {}",
+            left.type_name(),
+            op,
+            right.type_name()
+        )),
+        (left, right, op) => new_error(format!(
+            "unknown operator: {} {} {}",
+            left.type_name(),
+            op,
+            right.type_name()
+        )),
     }
 }
 
+fn host_equals(left: &Rc<dyn HostObject>, right: &Rc<dyn HostObject>) -> bool {
+    Rc::ptr_eq(left, right) || left.equals(right.as_ref())
+}
+
 fn eval_integer_infix_opr(op: &str, left: IntegerObject, right: IntegerObject) -> Option<Object> {
-    let result = match op {
-        "+" => Object::Integer(IntegerObject {
-            value: left.value + right.value,
-        }),
-        "-" => Object::Integer(IntegerObject {
-            value: left.value - right.value,
-        }),
-        "*" => Object::Integer(IntegerObject {
-            value: left.value * right.value,
-        }),
-        "/" => Object::Integer(IntegerObject {
-            value: left.value / right.value,
-        }),
-        "<" => Object::Boolean(to_native_bool(left.value < right.value)),
-        ">" => Object::Boolean(to_native_bool(left.value > right.value)),
-        "==" => Object::Boolean(to_native_bool(left.value == right.value)),
-        "!=" => Object::Boolean(to_native_bool(left.value != right.value)),
-        _ => Object::Null,
+    let (left, right) = (left.value, right.value);
+    let value = match op {
+        "+" => left.checked_add(right),
+        "-" => left.checked_sub(right),
+        "*" => left.checked_mul(right),
+        "/" if right == 0 => return new_error("division by zero"),
+        "/" => left.checked_div(right),
+        "<" => return Some(Object::Boolean(to_native_bool(left < right))),
+        ">" => return Some(Object::Boolean(to_native_bool(left > right))),
+        "==" => return Some(Object::Boolean(to_native_bool(left == right))),
+        "!=" => return Some(Object::Boolean(to_native_bool(left != right))),
+        _ => return new_error(format!("unknown operator: INTEGER {} INTEGER", op)),
     };
 
-    Some(result)
+    match value {
+        Some(value) => Some(Object::Integer(IntegerObject { value })),
+        None => new_error("integer overflow"),
+    }
 }
 
-fn eval_identifier(ident: &Identifier, env: Rc<RefCell<Environment>>) -> Option<Object> {
-    env.as_ref()
-        .borrow()
-        .get(ident.0.clone())
-        .map_or(None, |v| Some(v.as_ref().borrow().clone()))
+fn eval_identifier(
+    ident: &Identifier,
+    env: Rc<RefCell<Environment>>,
+    ctx: &mut Ctx,
+) -> Option<Object> {
+    let env = env.as_ref().borrow();
+    // A slot left unbound by a `let` that never ran falls back to the
+    // name, as without the resolver.
+    let value = match ident.slot {
+        Some(slot) => env.get_slot(slot).or_else(|| env.get(&ident.name)),
+        None => env.get(&ident.name),
+    };
+    match value {
+        Some(v) => Some(v.as_ref().borrow().clone()),
+        None => new_error(format!("identifier not found: {}", ident.name)),
+    }
 }
 
-fn eval_let_statement(stmt: &LetStatement, env: Rc<RefCell<Environment>>) -> Option<Object> {
-    let value = eval(&stmt.value, Rc::clone(&env))?;
-    if let Some(_) = env.borrow_mut().insert(stmt.name.0.clone(), value) {
-        return Some(Object::Null);
+// A `let` binds its name and evaluates to `null`, whether or not it
+// shadows an earlier binding.
+fn eval_let_statement(
+    stmt: &LetStatement,
+    env: Rc<RefCell<Environment>>,
+    ctx: &mut Ctx,
+) -> Option<Object> {
+    let mut value = eval_with(&stmt.value, Rc::clone(&env), ctx)?;
+    if value.is_error() {
+        return Some(value);
     }
-    None
+    if let Object::Function(f) = &mut value {
+        f.name
+            .get_or_insert_with(|| Rc::from(stmt.name.name.as_str()));
+    }
+    bind(&env, &stmt.name, value, ctx);
+    if let Err(err) = ctx.check_memory() {
+        return Some(Object::Error(err));
+    }
+    Some(Object::Null)
 }
 
-fn eval_return_statement(stmt: &ReturnStatement, env: Rc<RefCell<Environment>>) -> Option<Object> {
-    let value = eval(stmt.value.as_ref(), Rc::clone(&env)).unwrap();
+// Binds an identifier in `env`, using the slot assigned by the resolver when
+// there is one and falling back to a name lookup for unresolved programs.
+fn bind(
+    env: &Rc<RefCell<Environment>>,
+    ident: &Identifier,
+    value: Object,
+    ctx: &mut Ctx,
+) -> Option<Rc<RefCell<Object>>> {
+    if let Some(tracer) = ctx.tracer() {
+        tracer.bind(&ident.name, &value);
+    }
+    let mut env = env.borrow_mut();
+    match ident.slot {
+        Some(slot) => env.define(slot.index, &ident.name, value),
+        None => env.insert(ident.name.clone(), value),
+    }
+}
+
+fn eval_return_statement(
+    stmt: &ReturnStatement,
+    env: Rc<RefCell<Environment>>,
+    ctx: &mut Ctx,
+) -> Option<Object> {
+    let value = eval_with(stmt.value.as_ref(), Rc::clone(&env), ctx)?;
+    if value.is_error() {
+        return Some(value);
+    }
     Some(Object::Return(ReturnObject {
         value: Rc::new(value),
     }))
 }
 
-fn eval_function_literal(stmt: &FunctionLiteral, env: Rc<RefCell<Environment>>) -> Option<Object> {
+fn eval_function_literal(
+    stmt: &FunctionLiteral,
+    env: Rc<RefCell<Environment>>,
+    ctx: &mut Ctx,
+) -> Option<Object> {
     match stmt.body.as_ref() {
         Node::BlockStatement(v) => {
             return Some(Object::Function(FunctionLiteralObject {
+                name: None,
                 body: stmt.body.as_ref().clone(),
-                parameters: stmt.parameters.clone(), // TODO!: cloning a vec. Use references.
+                parameters: Rc::from(stmt.parameters.as_slice()), // TODO!: cloning a vec. Use references.
+                locals: Rc::clone(&stmt.locals),
+                env: Rc::clone(&env),
             }));
         }
         _ => {
@@ -213,6 +604,10 @@ fn eval_function_literal(stmt: &FunctionLiteral, env: Rc<RefCell<Environment>>)
     None
 }
 
+fn new_error<T: Into<String>>(message: T) -> Option<Object> {
+    Some(Object::Error(RuntimeError::new(message)))
+}
+
 fn to_native_bool(v: bool) -> BooleanObject {
     // TODO!: reference the TRUE and FALSE object instead of cloning.
     if v {
@@ -424,21 +819,130 @@ mod tests {
 
             match expected {
                 Some(test) => {
-                    let Object::Integer(actual) = result.expect("expected Some(Object), got=None") else {
+                    let Object::Integer(actual) = result.expect("expected Some(Object), got=None")
+                    else {
                         assert!(false, "expected Object::Integer, got=.");
                         return;
                     };
                     assert_eq!(actual.value, expected.unwrap());
                 }
                 None => {
+                    let result = result.expect("expected Some(Object), got=None");
                     assert!(
-                        result.is_none(),
-                        "expected evaluation of {} to be {:?}",
+                        matches!(result, Object::Null),
+                        "expected evaluation of {} to be null, got {}",
                         input,
-                        expected
+                        result
                     );
                 }
             }
         }
     }
+
+    #[test]
+    fn test_eval_while() {
+        let tests = vec![
+            ("while (false) { 1 }", "null"),
+            ("let f = fn() { while (true) { return 5; } }; f()", "5"),
+            (
+                "while (1 + true) {}",
+                "error: type mismatch: INTEGER + BOOLEAN",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let (parser, prog) = setup(input);
+            let result = eval(&prog, Environment::new()).expect("expected Some(Object)");
+            assert_eq!(result.to_string(), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_statement_values() {
+        let tests = vec![
+            ("null", "null"),
+            ("let a = 1;", "null"),
+            ("let a = 1; let a = 2;", "null"),
+            ("let a = 1; a; let b = 2;", "null"),
+            ("if (false) {}", "null"),
+            ("if (true) {}", "null"),
+            ("if (false) { 1 }", "null"),
+            ("if (null) { 1 } else { 2 }", "2"),
+            ("fn() {}()", "null"),
+            ("let f = fn() { let x = 1; }; f()", "null"),
+            ("", "null"),
+            ("!null", "true"),
+            ("let a = null; a", "null"),
+        ];
+
+        for (input, expected) in tests {
+            let (parser, prog) = setup(input);
+            let result = eval(&prog, Environment::new())
+                .unwrap_or_else(|| panic!("expected {} to evaluate to Some(Object)", input));
+            assert_eq!(result.to_string(), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_error_handling() {
+        let tests = vec![
+            ("5 + true;", "type mismatch: INTEGER + BOOLEAN"),
+            ("5 + true; 5;", "type mismatch: INTEGER + BOOLEAN"),
+            ("-true", "unknown operator: -BOOLEAN"),
+            ("true + false;", "unknown operator: BOOLEAN + BOOLEAN"),
+            ("5; true + false; 5", "unknown operator: BOOLEAN + BOOLEAN"),
+            (
+                "if (10 > 1) { true + false; }",
+                "unknown operator: BOOLEAN + BOOLEAN",
+            ),
+            (
+                "if (10 > 1) { if (10 > 1) { return true + false; } return 1; }",
+                "unknown operator: BOOLEAN + BOOLEAN",
+            ),
+            ("foobar", "identifier not found: foobar"),
+            ("1 / 0", "division by zero"),
+            ("2147483647 + 1", "integer overflow"),
+            ("let a = 1; a(2)", "not a function: INTEGER"),
+            (
+                "let f = fn(x) { x }; f()",
+                "wrong number of arguments: want=1, got=0",
+            ),
+            ("let f = fn(x) { x / 0 }; f(1) + 2", "division by zero"),
+            ("let a = -true; 1", "unknown operator: -BOOLEAN"),
+        ];
+
+        for (input, expected) in tests {
+            let (parser, prog) = setup(input);
+            let result = eval(&prog, Environment::new());
+            let Some(Object::Error(err)) = result else {
+                panic!("expected Object::Error for {}, got {:?}", input, result);
+            };
+            assert_eq!(err.message, expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_equality_across_types() {
+        let tests = vec![
+            ("true == true", "true"),
+            ("true != false", "true"),
+            ("null == null", "true"),
+            ("(1 < 2) == true", "true"),
+            ("\"a\" == \"a\"", "true"),
+            ("\"a\" != \"b\"", "true"),
+            ("\"foo\" + \"bar\"", "foobar"),
+            (
+                "let greet = fn(name) { \"hi \" + name }; greet(\"bob\")",
+                "hi bob",
+            ),
+            ("\"a\" + 1", "error: type mismatch: STRING + INTEGER"),
+            ("\"a\" - \"b\"", "error: unknown operator: STRING - STRING"),
+        ];
+
+        for (input, expected) in tests {
+            let (parser, prog) = setup(input);
+            let result = eval(&prog, Environment::new()).expect("expected Some(Object)");
+            assert_eq!(result.to_string(), expected, "input: {}", input);
+        }
+    }
 }
@@ -1,10 +1,13 @@
 use std::{
+    any::Any,
     borrow::Borrow,
     cell::{Ref, RefCell},
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
+    fmt,
     rc::Rc,
 };
 
+use super::{context::Ctx, environment::Environment};
 use crate::parser::ast;
 
 trait ObjectVariant {
@@ -15,13 +18,136 @@ trait ObjectVariant {
 pub enum Object {
     Integer(IntegerObject),
     Boolean(BooleanObject),
+    String(StringObject),
+    Array(ArrayObject),
+    Hash(HashObject),
     Identifier(IdentiferObject),
     Function(FunctionLiteralObject),
+    Builtin(BuiltinObject),
+    Host(Rc<dyn HostObject>),
     Return(ReturnObject),
     ObjectRef(Rc<RefCell<Object>>),
+    Error(RuntimeError),
     Null,
 }
 
+impl Object {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Integer(_) => "INTEGER",
+            Object::Boolean(_) => "BOOLEAN",
+            Object::String(_) => "STRING",
+            Object::Array(_) => "ARRAY",
+            Object::Hash(_) => "HASH",
+            Object::Identifier(_) => "IDENTIFIER",
+            Object::Function(_) => "FUNCTION",
+            Object::Builtin(_) => "BUILTIN",
+            Object::Host(v) => v.type_name(),
+            Object::Return(v) => v.value.type_name(),
+            Object::ObjectRef(v) => v.try_borrow().map_or("REF", |v| v.type_name()),
+            Object::Error(_) => "ERROR",
+            Object::Null => "NULL",
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Object::Error(_))
+    }
+
+    /// What the value counts for towards the memory limit on top of the
+    /// binding holding it: a unit per byte of a string, and per element
+    /// of an array or a hash besides what the elements count for.
+    pub fn size(&self) -> usize {
+        match self {
+            Object::String(v) => v.value.len(),
+            Object::Array(v) => v.elements.iter().map(|e| 1 + e.size()).sum(),
+            Object::Hash(v) => v
+                .pairs
+                .iter()
+                .map(|(key, value)| 1 + key.len() + value.size())
+                .sum(),
+            Object::Return(v) => v.value.size(),
+            _ => 0,
+        }
+    }
+
+    /// Like `to_string`, except that a string keeps its quotes, so `"1"`
+    /// and `1` can be told apart.
+    pub fn inspect(&self) -> String {
+        match self {
+            Object::String(v) => v.inspect(),
+            other => other.to_string(),
+        }
+    }
+
+    // References that are already being printed further up are shown as
+    // `...` instead of being followed again.
+    fn fmt_seen(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        seen: &mut Vec<*const RefCell<Object>>,
+    ) -> fmt::Result {
+        match self {
+            Object::Integer(v) => write!(f, "{}", v.inspect()),
+            Object::Boolean(v) => write!(f, "{}", v.inspect()),
+            Object::String(v) => write!(f, "{}", v.inspect()),
+            Object::Array(v) => {
+                write!(f, "[")?;
+                for (i, element) in v.elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.fmt_seen(f, seen)?;
+                }
+                write!(f, "]")
+            }
+            Object::Hash(v) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in v.pairs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", ast::quote_string(key))?;
+                    value.fmt_seen(f, seen)?;
+                }
+                write!(f, "}}")
+            }
+            Object::Identifier(v) => write!(f, "{}", v.inspect()),
+            Object::Function(v) => write!(f, "{}", v.inspect()),
+            Object::Builtin(v) => write!(f, "{}", v.inspect()),
+            Object::Host(v) => write!(f, "{}", v),
+            Object::Return(v) => v.value.fmt_seen(f, seen),
+            Object::ObjectRef(v) => {
+                let ptr = Rc::as_ptr(v);
+                if seen.contains(&ptr) {
+                    return write!(f, "...");
+                }
+                let Ok(inner) = v.try_borrow() else {
+                    return write!(f, "...");
+                };
+                seen.push(ptr);
+                let result = inner.fmt_seen(f, seen);
+                seen.pop();
+                result
+            }
+            Object::Error(v) => write!(f, "error: {}", v),
+            Object::Null => write!(f, "null"),
+        }
+    }
+}
+
+/// Renders values the way a user of the language writes them, e.g. `5`,
+/// `true`, `null` or `fn(x, y) { ... }`. A string is printed as its
+/// contents, while strings inside arrays and hashes keep their quotes.
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Object::String(v) => write!(f, "{}", v.value),
+            other => other.fmt_seen(f, &mut vec![]),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct IntegerObject {
     pub value: i32,
@@ -44,6 +170,28 @@ impl ObjectVariant for BooleanObject {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct StringObject {
+    pub value: String,
+}
+
+impl ObjectVariant for StringObject {
+    fn inspect(&self) -> String {
+        ast::quote_string(&self.value)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArrayObject {
+    pub elements: Vec<Object>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HashObject {
+    // ordered so that printing a hash is deterministic
+    pub pairs: BTreeMap<String, Object>,
+}
+
 #[derive(Debug, Clone)]
 pub struct IdentiferObject {
     pub value: String,
@@ -55,16 +203,120 @@ impl ObjectVariant for IdentiferObject {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct FunctionLiteralObject {
+    // name of the `let` the function was first bound by, for backtraces
+    pub name: Option<Rc<str>>,
     // fn ( <Identifer[]> ) { <BlockStatement>}
-    pub parameters: Vec<ast::Node>,
+    pub parameters: Rc<[ast::Node]>,
     pub body: ast::Node,
+    // names of the slots of a call, see `ast::FunctionLiteral`
+    pub locals: Rc<Vec<String>>,
+    // environment the function was defined in
+    pub env: Rc<RefCell<Environment>>,
+}
+
+// `env` is left out: a recursive function is bound inside its own
+// environment, so printing it would never terminate.
+impl std::fmt::Debug for FunctionLiteralObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FunctionLiteralObject")
+            .field("parameters", &self.parameters)
+            .field("body", &self.body)
+            .finish_non_exhaustive()
+    }
 }
 
 impl ObjectVariant for FunctionLiteralObject {
     fn inspect(&self) -> String {
-        todo!()
+        let parameters = self
+            .parameters
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!("fn({parameters}) {{ ... }}")
+    }
+}
+
+/// How many arguments a builtin function accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Fixed(usize),
+    Variadic { min: usize },
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::Fixed(n) => count == n,
+            Arity::Variadic { min } => count >= min,
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arity::Fixed(n) => write!(f, "{}", n),
+            Arity::Variadic { min } => write!(f, "{}+", min),
+        }
+    }
+}
+
+pub type NativeFn = dyn Fn(&mut Ctx, &[Object]) -> Result<Object, RuntimeError>;
+
+/// A function implemented by the host and called like any other function.
+#[derive(Clone)]
+pub struct BuiltinObject {
+    pub name: String,
+    pub arity: Arity,
+    pub func: Rc<NativeFn>,
+}
+
+impl std::fmt::Debug for BuiltinObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BuiltinObject")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ObjectVariant for BuiltinObject {
+    fn inspect(&self) -> String {
+        format!("<builtin {}>", self.name)
+    }
+}
+
+/// A Rust value handed to a program as an opaque object. Programs can
+/// only read its properties with `obj.name`, call its methods with
+/// `obj.name(args)`, print it and compare it with `==`.
+pub trait HostObject: Any + fmt::Debug + fmt::Display {
+    /// Name reported for the object in error messages.
+    fn type_name(&self) -> &'static str;
+
+    /// Value of the property `name`, or `None` when there is no such
+    /// property.
+    fn get(&self, name: &str) -> Option<Object> {
+        None
+    }
+
+    /// Calls the method `name`, or returns `None` when there is no such
+    /// method. Arguments are not checked before the call.
+    fn call_method(
+        &self,
+        ctx: &mut Ctx,
+        name: &str,
+        args: &[Object],
+    ) -> Option<Result<Object, RuntimeError>> {
+        None
+    }
+
+    /// Whether `self == other` holds in a program. Only called when the
+    /// two are not the same object, which is always equal to itself.
+    fn equals(&self, other: &dyn HostObject) -> bool {
+        false
     }
 }
 
@@ -75,9 +327,102 @@ pub struct ReturnObject {
 
 impl ObjectVariant for ReturnObject {
     fn inspect(&self) -> String {
-        todo!()
+        self.value.to_string()
     }
 }
 
+/// An error raised while evaluating a program, e.g. `type mismatch:
+/// INTEGER + BOOLEAN`. It travels up through `eval` as `Object::Error`
+/// and stops evaluation of the enclosing statements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub message: String,
+    /// Source line of the expression that failed, once known.
+    pub line: Option<usize>,
+    /// The calls of functions written in the language that the error
+    /// came out of, innermost first. Empty for errors outside functions.
+    pub backtrace: Vec<Frame>,
+}
+
+impl RuntimeError {
+    pub fn new<T: Into<String>>(message: T) -> Self {
+        RuntimeError {
+            message: message.into(),
+            line: None,
+            backtrace: vec![],
+        }
+    }
+}
+
+/// A call in the backtrace of a `RuntimeError`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// The name the function was bound to with `let`, or `<anonymous>`.
+    pub function: String,
+    /// Line of the call.
+    pub line: Option<usize>,
+    /// The arguments as `Object::inspect` shows them.
+    pub args: Vec<String>,
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.function, self.args.join(", "))?;
+        match self.line {
+            Some(line) => write!(f, " called on line {}", line),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
 pub const TRUE: BooleanObject = BooleanObject { value: true };
 pub const FALSE: BooleanObject = BooleanObject { value: false };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluator::eval, utils::setup};
+
+    #[test]
+    fn test_object_display() {
+        let tests = vec![
+            ("5", "5"),
+            ("-10", "-10"),
+            ("true", "true"),
+            ("1 > 2", "false"),
+            ("fn(x, y) { x + y }", "fn(x, y) { ... }"),
+            ("fn() { 1 }", "fn() { ... }"),
+            ("let f = fn(a) { a }; f", "fn(a) { ... }"),
+            ("let a = 1; let a = 2;", "null"),
+        ];
+
+        for (input, expected) in tests {
+            let (_, prog) = setup(input);
+            let result = eval(&prog, Environment::new()).expect("expected Some(Object)");
+            assert_eq!(result.to_string(), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_object_display_nested() {
+        let ret = Object::Return(ReturnObject {
+            value: Rc::new(Object::Integer(IntegerObject { value: 7 })),
+        });
+        assert_eq!(ret.to_string(), "7");
+
+        let cell = Rc::new(RefCell::new(Object::Null));
+        *cell.borrow_mut() = Object::ObjectRef(Rc::clone(&cell));
+        assert_eq!(Object::ObjectRef(Rc::clone(&cell)).to_string(), "...");
+
+        let inner = Rc::new(RefCell::new(Object::Boolean(TRUE)));
+        assert_eq!(Object::ObjectRef(inner).to_string(), "true");
+    }
+}
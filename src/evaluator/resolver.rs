@@ -0,0 +1,369 @@
+use super::environment::Environment;
+use crate::parser::ast::*;
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+// One scope per function body. `if` blocks share the scope of the enclosing
+// function, the same way the evaluator shares its environment with them.
+#[derive(Debug)]
+struct Scope {
+    names: HashMap<String, usize>,
+    next: usize,
+}
+
+impl Scope {
+    fn new() -> Self {
+        Scope {
+            names: HashMap::new(),
+            next: 0,
+        }
+    }
+
+    fn from_env(env: &Environment) -> Self {
+        Scope {
+            names: env
+                .declared()
+                .into_iter()
+                .map(|(name, idx)| (name.to_string(), idx))
+                .collect(),
+            next: env.slots.len(),
+        }
+    }
+
+    // The name of each slot, in order.
+    fn locals(&self) -> Rc<Vec<String>> {
+        let mut locals = vec![String::new(); self.next];
+        for (name, idx) in &self.names {
+            locals[*idx] = name.clone();
+        }
+        Rc::new(locals)
+    }
+
+    fn declare(&mut self, name: &str) -> usize {
+        if let Some(idx) = self.names.get(name) {
+            return *idx;
+        }
+        let idx = self.next;
+        self.names.insert(name.to_string(), idx);
+        self.next += 1;
+        idx
+    }
+}
+
+/// Static pass that annotates every `Identifier` with the `Slot` it will
+/// occupy at runtime, so the evaluator can index environments directly
+/// instead of looking names up. References to names that are never
+/// declared are reported in `errors` before anything is evaluated, except
+/// inside function and test bodies: those run later, when a name bound
+/// after them (e.g. by mutual recursion, or a later REPL input) may
+/// exist, so they are left for the evaluator to look up by name.
+pub struct Resolver {
+    scopes: Vec<Scope>,
+    // number of function and test bodies being resolved
+    deferred: usize,
+    pub errors: Vec<String>,
+}
+
+impl Resolver {
+    /// Creates a resolver whose outermost scopes mirror `env` and the
+    /// environments it extends, so programs can be resolved incrementally
+    /// against bindings that already exist (e.g. in the REPL).
+    pub fn new(env: &Rc<RefCell<Environment>>) -> Self {
+        let mut scopes = vec![];
+        let mut current = Some(Rc::clone(env));
+        while let Some(env) = current {
+            let env = env.borrow();
+            scopes.push(Scope::from_env(&env));
+            current = env.outer.as_ref().map(Rc::clone);
+        }
+        scopes.reverse();
+
+        Resolver {
+            scopes,
+            deferred: 0,
+            errors: vec![],
+        }
+    }
+
+    pub fn resolve_program(&mut self, node: &mut Node) {
+        self.resolve(node);
+    }
+
+    fn resolve(&mut self, node: &mut Node) {
+        match node {
+            Node::Program(p) => self.resolve_statements(&mut p.statements),
+            Node::BlockStatement(b) => self.resolve_statements(&mut b.statements),
+            Node::LetStatement(v) => self.resolve_let_statement(v),
+            Node::ReturnStatement(v) => self.resolve(Rc::make_mut(&mut v.value)),
+            Node::IfExpression(v) => {
+                self.resolve(Rc::make_mut(&mut v.condition));
+                self.resolve_statements(&mut v.consequence);
+                self.resolve_statements(&mut v.alternative);
+            }
+            Node::While(v) => {
+                self.resolve(Rc::make_mut(&mut v.condition));
+                self.resolve_statements(&mut v.body);
+            }
+            Node::Function(v) => self.resolve_function_literal(v),
+            Node::Test(v) => {
+                self.scopes.push(Scope::new());
+                self.deferred += 1;
+                self.resolve(Rc::make_mut(&mut v.body));
+                self.deferred -= 1;
+                self.scopes.pop();
+            }
+            Node::CallExpression(v) => {
+                self.resolve(Rc::make_mut(&mut v.function));
+                self.resolve_statements(&mut v.arguments);
+            }
+            Node::Member(v) => self.resolve(Rc::make_mut(&mut v.object)),
+            Node::Ident(v) => self.resolve_identifier(v),
+            Node::Prefix(v) => self.resolve(Rc::make_mut(&mut v.right)),
+            Node::Infix(v) => {
+                self.resolve(Rc::make_mut(&mut v.left));
+                self.resolve(Rc::make_mut(&mut v.right));
+            }
+            Node::Int(_)
+            | Node::Boolean(_)
+            | Node::Str(_)
+            | Node::Null
+            | Node::Comment(_)
+            | Node::Nil => {}
+        }
+    }
+
+    fn resolve_statements(&mut self, statements: &mut [Node]) {
+        for stmt in statements {
+            self.resolve(stmt);
+        }
+    }
+
+    fn resolve_let_statement(&mut self, stmt: &mut LetStatement) {
+        // a function may refer to the name it is being bound to, anything
+        // else only sees the name once the value has been computed
+        if let Node::Function(_) = stmt.value.as_ref() {
+            self.declare(&mut stmt.name);
+            self.resolve(Rc::make_mut(&mut stmt.value));
+        } else {
+            self.resolve(Rc::make_mut(&mut stmt.value));
+            self.declare(&mut stmt.name);
+        }
+    }
+
+    fn resolve_function_literal(&mut self, func: &mut FunctionLiteral) {
+        self.scopes.push(Scope::new());
+        self.deferred += 1;
+        for param in func.parameters.iter_mut() {
+            if let Node::Ident(ident) = param {
+                self.declare(ident);
+            }
+        }
+        self.resolve(Rc::make_mut(&mut func.body));
+        self.deferred -= 1;
+        let scope = self.scopes.pop().expect("the scope of the function");
+        func.locals = scope.locals();
+    }
+
+    fn resolve_identifier(&mut self, ident: &mut Identifier) {
+        let found = self
+            .scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, scope)| scope.names.get(&ident.name).map(|idx| (depth, *idx)));
+
+        match found {
+            Some((depth, index)) => ident.slot = Some(Slot { depth, index }),
+            None if self.deferred > 0 => ident.slot = None,
+            None if ident.line > 0 => self.errors.push(format!(
+                "line {}: identifier not found: {}",
+                ident.line, ident.name
+            )),
+            None => self
+                .errors
+                .push(format!("identifier not found: {}", ident.name)),
+        }
+    }
+
+    fn declare(&mut self, ident: &mut Identifier) {
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("resolver always has a global scope");
+        let index = scope.declare(&ident.name);
+        ident.slot = Some(Slot { depth: 0, index });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        evaluator::{eval, object::Object},
+        utils::setup,
+    };
+
+    fn resolve(input: &str, env: &Rc<RefCell<Environment>>) -> (Node, Vec<String>) {
+        let (_, mut prog) = setup(input);
+        let mut resolver = Resolver::new(env);
+        resolver.resolve_program(&mut prog);
+        (prog, resolver.errors)
+    }
+
+    fn statement(prog: &Node, idx: usize) -> &Node {
+        let Node::Program(prog) = prog else {
+            panic!("prog is not Node::Program");
+        };
+        &prog.statements[idx]
+    }
+
+    #[test]
+    fn test_resolve_assigns_slots() {
+        let env = Environment::new();
+        let (prog, errors) = resolve(
+            "let a = 1; let b = 2; let f = fn(x) { let y = x; a + y }; let a = 3;",
+            &env,
+        );
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+
+        let tests = vec![(0, "a", 0), (1, "b", 1), (2, "f", 2), (3, "a", 0)];
+        for (idx, name, index) in tests {
+            let Node::LetStatement(stmt) = statement(&prog, idx) else {
+                panic!("expected Node::LetStatement");
+            };
+            assert_eq!(stmt.name.name, name);
+            assert_eq!(stmt.name.slot, Some(Slot { depth: 0, index }));
+        }
+
+        let Node::LetStatement(stmt) = statement(&prog, 2) else {
+            panic!("expected Node::LetStatement");
+        };
+        let Node::Function(func) = stmt.value.as_ref() else {
+            panic!("expected Node::Function");
+        };
+        let Node::BlockStatement(body) = func.body.as_ref() else {
+            panic!("expected Node::BlockStatement");
+        };
+        let Node::Infix(sum) = &body.statements[1] else {
+            panic!("expected Node::Infix");
+        };
+        let (Node::Ident(a), Node::Ident(y)) = (sum.left.as_ref(), sum.right.as_ref()) else {
+            panic!("expected Node::Ident operands");
+        };
+        assert_eq!(a.slot, Some(Slot { depth: 1, index: 0 }));
+        assert_eq!(y.slot, Some(Slot { depth: 0, index: 1 }));
+    }
+
+    #[test]
+    fn test_resolve_reports_undeclared_identifiers() {
+        let tests = vec![
+            ("x;", vec!["line 1: identifier not found: x"]),
+            ("let a = a;", vec!["line 1: identifier not found: a"]),
+            (
+                "let f = fn() { y }; f() + z;",
+                vec!["line 1: identifier not found: z"],
+            ),
+            ("let f = fn(n) { f(n) };", vec![]),
+        ];
+
+        for (input, expected) in tests {
+            let (_, errors) = resolve(input, &Environment::new());
+            assert_eq!(errors, expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_resolve_against_existing_environment() {
+        let env = Environment::new();
+        let (prog, errors) = resolve("let a = 5;", &env);
+        assert!(errors.is_empty());
+        eval(&prog, Rc::clone(&env));
+
+        let (prog, errors) = resolve("let b = a * 2; b;", &env);
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+        let Some(Object::Integer(actual)) = eval(&prog, Rc::clone(&env)) else {
+            panic!("expected Object::Integer");
+        };
+        assert_eq!(actual.value, 10);
+
+        let (prog, errors) = resolve("let f = fn() { later };", &env);
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+        eval(&prog, Rc::clone(&env));
+        let (prog, errors) = resolve("let later = 3; f();", &env);
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+        let Some(Object::Integer(actual)) = eval(&prog, Rc::clone(&env)) else {
+            panic!("expected Object::Integer");
+        };
+        assert_eq!(actual.value, 3);
+    }
+
+    #[test]
+    fn test_eval_let_that_never_ran() {
+        let tests = vec![
+            (
+                "if (false) { let x = 1; } x;",
+                Some("identifier not found: x"),
+            ),
+            (
+                "if (false) { let x = 1; } let z = 2; x;",
+                Some("identifier not found: x"),
+            ),
+            (
+                "let f = fn() { if (false) { let x = 1; } let z = 2; x }; f();",
+                Some("identifier not found: x"),
+            ),
+            (
+                "let x = 5; let f = fn() { if (false) { let x = 1; } let z = 2; x }; f();",
+                None,
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let env = Environment::new();
+            let (prog, _) = resolve(input, &env);
+            match (eval(&prog, env), expected) {
+                (Some(Object::Error(err)), Some(expected)) => {
+                    assert_eq!(err.message, expected, "input: {}", input)
+                }
+                (Some(Object::Integer(actual)), None) => {
+                    assert_eq!(actual.value, 5, "input: {}", input)
+                }
+                (actual, _) => panic!("unexpected result for {}: {:?}", input, actual),
+            }
+        }
+    }
+
+    #[test]
+    fn test_eval_resolved_programs() {
+        let tests =
+            vec![
+            ("let a = 10; a;", 10),
+            ("let x = 2; let y = 4; x + y;", 6),
+            ("let global = 100; let f = fn (a) { return a + global }; f(10);", 110),
+            (
+                "let fact = fn(n) { if (n < 2) { return 1; } return n * fact(n - 1); }; fact(5);",
+                120,
+            ),
+            (
+                "let adder = fn(x) { fn(y) { x + y } }; let addTwo = adder(2); addTwo(3);",
+                5,
+            ),
+            ("let a = 1; let f = fn() { a }; let a = 2; f();", 2),
+            (
+                "let even = fn(n) { if (n == 0) { true } else { odd(n - 1) } }; \
+                 let odd = fn(n) { if (n == 0) { false } else { even(n - 1) } }; \
+                 if (even(10)) { 1 } else { 0 };",
+                1,
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let env = Environment::new();
+            let (prog, errors) = resolve(input, &env);
+            assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+            let Some(Object::Integer(actual)) = eval(&prog, env) else {
+                panic!("expected Object::Integer for {}", input);
+            };
+            assert_eq!(actual.value, expected, "input: {}", input);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use super::object::Object;
+use crate::parser::ast::Node;
+use std::{fmt, io::Write};
+
+/// Writes a line for every node evaluated, every function call and
+/// return, and every binding made. Each line starts with the nesting
+/// depth of the evaluation and the source line when it is known, and is
+/// indented by the depth:
+///
+/// ```text
+///   2    1 |     Infix +
+///   3    1 |       Ident a
+/// ```
+pub struct Tracer {
+    output: Box<dyn Write>,
+    depth: usize,
+    // lines of the nodes being evaluated that have one, with 0 for the
+    // start of a function body, which is not on the line of the call
+    lines: Vec<usize>,
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tracer")
+            .field("depth", &self.depth)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Tracer {
+    pub fn new(output: Box<dyn Write>) -> Self {
+        Tracer {
+            output,
+            depth: 0,
+            lines: vec![],
+        }
+    }
+
+    /// Logs the evaluation of `node`, whose children are logged one level
+    /// deeper until `exit` is called.
+    pub(crate) fn enter(&mut self, node: &Node) {
+        if let Some(line) = node.line() {
+            self.lines.push(line);
+        }
+        self.event(format_args!("{}", node.label()));
+        self.depth += 1;
+    }
+
+    pub(crate) fn exit(&mut self, node: &Node) {
+        self.depth -= 1;
+        if node.line().is_some() {
+            self.lines.pop();
+        }
+    }
+
+    pub(crate) fn call(&mut self, name: &str, args: &[Object]) {
+        let args = args
+            .iter()
+            .map(Object::inspect)
+            .collect::<Vec<String>>()
+            .join(", ");
+        self.event(format_args!("call {}({})", name, args));
+        self.lines.push(0);
+    }
+
+    pub(crate) fn ret(&mut self, name: &str, result: Option<&Object>) {
+        self.lines.pop();
+        match result {
+            Some(Object::Error(err)) => self.event(format_args!("error from {}: {}", name, err)),
+            Some(Object::Return(v)) => {
+                self.event(format_args!("return from {}: {}", name, v.value.inspect()))
+            }
+            Some(value) => self.event(format_args!("return from {}: {}", name, value.inspect())),
+            None => self.event(format_args!("return from {}: null", name)),
+        }
+    }
+
+    pub(crate) fn bind(&mut self, name: &str, value: &Object) {
+        self.event(format_args!("bind {} = {}", name, value.inspect()));
+    }
+
+    // Trace output is best effort: a failed write must not change what
+    // the program does.
+    fn event(&mut self, event: fmt::Arguments) {
+        let line = match self.lines.last() {
+            Some(line) if *line > 0 => line.to_string(),
+            _ => String::from("-"),
+        };
+        let _ = writeln!(
+            self.output,
+            "{:>3} {:>4} | {}{}",
+            self.depth,
+            line,
+            "  ".repeat(self.depth),
+            event
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Interpreter, OutputBuffer};
+
+    #[test]
+    fn test_trace() {
+        let trace = OutputBuffer::new();
+        let mut interpreter = Interpreter::new().with_trace(trace.clone());
+        interpreter
+            .eval("let inc = fn(n) {\n  n + 1\n};\ninc(2)")
+            .unwrap();
+
+        let expected = [
+            "  0    - | Program",
+            "  1    1 |   Let inc",
+            "  2    1 |     Function(n)",
+            "  2    1 |     bind inc = fn(n) { ... }",
+            "  1    4 |   Call",
+            "  2    4 |     Ident inc",
+            "  2    4 |     Int 2",
+            "  2    4 |     call inc(2)",
+            "  2    - |     bind n = 2",
+            "  2    - |     Block",
+            "  3    2 |       Infix +",
+            "  4    2 |         Ident n",
+            "  4    2 |         Int 1",
+            "  2    4 |     return from inc: 3",
+            "",
+        ];
+        assert_eq!(trace.contents(), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_trace_reports_errors() {
+        let trace = OutputBuffer::new();
+        let mut interpreter = Interpreter::new().with_trace(trace.clone());
+        interpreter
+            .eval("let f = fn() { 1 + true }; f()")
+            .unwrap_err();
+        let contents = trace.contents();
+        assert!(
+            contents.ends_with("error from f: type mismatch: INTEGER + BOOLEAN\n"),
+            "{}",
+            contents
+        );
+    }
+}
@@ -0,0 +1,362 @@
+use crate::{
+    lexer::Lexer,
+    parser::{ast::*, precedence_of, Parser, PrecedenceType},
+};
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Column after which call arguments are broken onto separate lines.
+    pub line_width: usize,
+    /// Number of spaces per indentation level.
+    pub indent_width: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            line_width: 80,
+            indent_width: 4,
+        }
+    }
+}
+
+/// Parses `input` and prints it back in canonical form. Parser errors are
+/// returned instead, since formatting a partial tree would drop code.
+pub fn format_source(input: &str, config: &Config) -> Result<String, Vec<String>> {
+    let lexer = Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let prog = parser.parse_program();
+
+    if !parser.errors.is_empty() {
+        return Err(parser.errors);
+    }
+    Ok(format_program(&prog, config))
+}
+
+/// Prints a program with one statement per line, indented blocks, the
+/// comments kept by the parser and only the parentheses that the
+/// operator precedences require.
+pub fn format_program(node: &Node, config: &Config) -> String {
+    let Node::Program(prog) = node else {
+        return Printer { config }.expr(node, 0, 0);
+    };
+
+    let mut out = Printer { config }.statements(&prog.statements, 0);
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+struct Printer<'a> {
+    config: &'a Config,
+}
+
+impl Printer<'_> {
+    fn indent(&self, level: usize) -> String {
+        " ".repeat(level * self.config.indent_width)
+    }
+
+    fn statements(&self, stmts: &[Node], level: usize) -> String {
+        let mut lines: Vec<String> = vec![];
+        for stmt in stmts {
+            match stmt {
+                Node::Nil => {}
+                Node::Comment(c) if c.trailing && !lines.is_empty() => {
+                    let last = lines.last_mut().unwrap();
+                    last.push(' ');
+                    last.push_str(&c.text);
+                }
+                stmt => lines.push(format!(
+                    "{}{}",
+                    self.indent(level),
+                    self.statement(stmt, level)
+                )),
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn statement(&self, stmt: &Node, level: usize) -> String {
+        let column = level * self.config.indent_width;
+        match stmt {
+            Node::LetStatement(v) => {
+                let prefix = format!("let {} = ", v.name.name);
+                let value = self.expr(&v.value, level, column + prefix.len());
+                format!("{prefix}{value};")
+            }
+            Node::ReturnStatement(v) => {
+                format!("return {};", self.expr(&v.value, level, column + 7))
+            }
+            Node::Comment(v) => v.text.clone(),
+            Node::Test(v) => {
+                let body = match v.body.as_ref() {
+                    Node::BlockStatement(b) => self.block(&b.statements, level),
+                    body => self.block(std::slice::from_ref(body), level),
+                };
+                format!("test {} {}", quote_string(&v.name), body)
+            }
+            Node::IfExpression(_) | Node::While(_) => self.expr(stmt, level, column),
+            _ => format!("{};", self.expr(stmt, level, column)),
+        }
+    }
+
+    fn block(&self, stmts: &[Node], level: usize) -> String {
+        if stmts.is_empty() {
+            return String::from("{}");
+        }
+        format!(
+            "{{\n{}\n{}}}",
+            self.statements(stmts, level + 1),
+            self.indent(level)
+        )
+    }
+
+    // `column` is where the expression starts on its first line, used to
+    // decide whether call arguments fit within the line width.
+    fn expr(&self, node: &Node, level: usize, column: usize) -> String {
+        match node {
+            Node::Int(v) => v.0.to_string(),
+            Node::Boolean(v) => v.0.to_string(),
+            Node::Str(v) => quote_string(&v.0),
+            Node::Null => String::from("null"),
+            Node::Ident(v) => v.name.clone(),
+            Node::Prefix(v) => {
+                let right = self.operand(&v.right, level, column + v.op.len(), |p| {
+                    p < PrecedenceType::PREFIX
+                });
+                format!("{}{}", v.op, right)
+            }
+            Node::Infix(v) => {
+                let precedence = precedence_of(&v.op);
+                let left = self.operand(&v.left, level, column, |p| p < precedence);
+                let column = column + last_line_len(&left) + v.op.len() + 2;
+                let right = self.operand(&v.right, level, column, |p| p <= precedence);
+                format!("{} {} {}", left, v.op, right)
+            }
+            Node::CallExpression(v) => {
+                let function =
+                    self.operand(&v.function, level, column, |p| p < PrecedenceType::CALL);
+                let column = column + last_line_len(&function);
+                format!(
+                    "{}{}",
+                    function,
+                    self.arguments(&v.arguments, level, column)
+                )
+            }
+            Node::Member(v) => {
+                let object = self.operand(&v.object, level, column, |p| p < PrecedenceType::CALL);
+                format!("{}.{}", object, v.property)
+            }
+            Node::Function(v) => {
+                let parameters = v
+                    .parameters
+                    .iter()
+                    .map(|p| self.expr(p, level, column))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                let body = match v.body.as_ref() {
+                    Node::BlockStatement(b) => self.block(&b.statements, level),
+                    body => self.block(std::slice::from_ref(body), level),
+                };
+                format!("fn({parameters}) {body}")
+            }
+            Node::IfExpression(v) => {
+                let condition = self.expr(&v.condition, level, column + 4);
+                let mut out = format!("if ({}) {}", condition, self.block(&v.consequence, level));
+                if !v.alternative.is_empty() {
+                    out.push_str(" else ");
+                    out.push_str(&self.block(&v.alternative, level));
+                }
+                out
+            }
+            Node::While(v) => {
+                let condition = self.expr(&v.condition, level, column + 7);
+                format!("while ({}) {}", condition, self.block(&v.body, level))
+            }
+            Node::Program(v) => self.statements(&v.statements, level),
+            Node::BlockStatement(v) => self.block(&v.statements, level),
+            Node::LetStatement(_) | Node::ReturnStatement(_) | Node::Test(_) | Node::Comment(_) => {
+                self.statement(node, level)
+            }
+            Node::Nil => String::new(),
+        }
+    }
+
+    // Prints an operand, wrapped in parentheses when `needs_parens` says its
+    // precedence is too low to stand unwrapped next to its operator.
+    fn operand(
+        &self,
+        node: &Node,
+        level: usize,
+        column: usize,
+        needs_parens: impl Fn(PrecedenceType) -> bool,
+    ) -> String {
+        if needs_parens(precedence(node)) {
+            format!("({})", self.expr(node, level, column + 1))
+        } else {
+            self.expr(node, level, column)
+        }
+    }
+
+    fn arguments(&self, args: &[Node], level: usize, column: usize) -> String {
+        let flat = args
+            .iter()
+            .map(|arg| self.expr(arg, level, column))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let first_line = flat.lines().next().unwrap_or_default();
+        if column + first_line.len() + 2 <= self.config.line_width {
+            return format!("({flat})");
+        }
+
+        let indent = self.indent(level + 1);
+        let args = args
+            .iter()
+            .map(|arg| format!("{}{}", indent, self.expr(arg, level + 1, indent.len())))
+            .collect::<Vec<String>>()
+            .join(",\n");
+        format!("(\n{}\n{})", args, self.indent(level))
+    }
+}
+
+fn precedence(node: &Node) -> PrecedenceType {
+    match node {
+        Node::Infix(v) => precedence_of(&v.op),
+        Node::Prefix(_) => PrecedenceType::PREFIX,
+        _ => PrecedenceType::CALL,
+    }
+}
+
+fn last_line_len(s: &str) -> usize {
+    s.lines().last().map_or(0, str::len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(input: &str) -> String {
+        format_source(input, &Config::default()).expect("input should parse")
+    }
+
+    #[test]
+    fn test_format_statements() {
+        let tests = vec![
+            ("let   a=1 ;a", "let a = 1;\na;\n"),
+            ("return  true", "return true;\n"),
+            (
+                "let f = fn(x,y){ let z = x; return z+y; };",
+                "let f = fn(x, y) {\n    let z = x;\n    return z + y;\n};\n",
+            ),
+            (
+                "if (a < b) { a } else { b }",
+                "if (a < b) {\n    a;\n} else {\n    b;\n}\n",
+            ),
+            ("if (a) {}", "if (a) {}\n"),
+            ("while(x){ f(x) }", "while (x) {\n    f(x);\n}\n"),
+            (
+                "test \"it works\" { assert(true) }",
+                "test \"it works\" {\n    assert(true);\n}\n",
+            ),
+            ("fn(){}(  )", "fn() {}();\n"),
+            ("", ""),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(format(input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_format_minimal_parentheses() {
+        let tests = vec![
+            ("-10 + 2", "-10 + 2;\n"),
+            ("((a + b)) + c", "a + b + c;\n"),
+            ("a + (b + c)", "a + (b + c);\n"),
+            ("a - (b - c)", "a - (b - c);\n"),
+            ("(a + b) * c", "(a + b) * c;\n"),
+            ("a + (b * c)", "a + b * c;\n"),
+            ("-(a + b)", "-(a + b);\n"),
+            ("!(-a)", "!-a;\n"),
+            ("(5 > 4) == (3 < 4)", "5 > 4 == 3 < 4;\n"),
+            ("(f)(x) * (g(y))", "f(x) * g(y);\n"),
+            ("(fn(x) { x })(1)", "fn(x) {\n    x;\n}(1);\n"),
+            ("(p.x) + (-q).y", "p.x + (-q).y;\n"),
+            ("(p.scale(2)).x", "p.scale(2).x;\n"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(format(input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_format_preserves_comments() {
+        let input = "// leading
+let a = 1; // trailing
+let f = fn(x) {
+    // inside
+    x + a // after x
+};
+// last";
+        let expected = "// leading
+let a = 1; // trailing
+let f = fn(x) {
+    // inside
+    x + a; // after x
+};
+// last
+";
+        assert_eq!(format(input), expected);
+    }
+
+    #[test]
+    fn test_format_line_width_and_indent() {
+        let config = Config {
+            line_width: 20,
+            indent_width: 2,
+        };
+        let input = "let r = sum(first, second, third); if (x) { f(aaaa, bbbb, cccc, dddd) }";
+        let expected = "let r = sum(
+  first,
+  second,
+  third
+);
+if (x) {
+  f(
+    aaaa,
+    bbbb,
+    cccc,
+    dddd
+  );
+}
+";
+        assert_eq!(format_source(input, &config).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_format_is_idempotent() {
+        let tests = vec![
+            "let a = 1 + // inside an expression
+             2;",
+            "if (x) { // after brace
+             1 } // after if
+             else { 2 }",
+            "let f = fn(a, b) { if (a > b) { return a; } return b * (a - b); }; f(1, 2)",
+            "longFunctionName(argumentNumberOne, argumentNumberTwo, fn(x) { x }, argumentNumberFour)",
+            "!(-a * (b + c)) == d",
+        ];
+
+        for input in tests {
+            let once = format(input);
+            let twice = format(&once);
+            assert_eq!(once, twice, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_format_rejects_parse_errors() {
+        assert!(format_source("let = 5;", &Config::default()).is_err());
+    }
+}
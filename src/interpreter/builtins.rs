@@ -0,0 +1,387 @@
+use super::host::{FromObject, IntoObject};
+use crate::evaluator::{
+    context::Ctx,
+    io::{io_error, IoPolicy},
+    object::*,
+};
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    rc::Rc,
+};
+
+type Builtin = fn(&mut Ctx, &[Object]) -> Result<Object, RuntimeError>;
+
+/// Functions bound in the global environment of every interpreter. The
+/// ones doing I/O check the interpreter's `IoPolicy` first.
+pub(super) const BUILTINS: &[(&str, Arity, Builtin)] = &[
+    ("puts", Arity::Variadic { min: 0 }, puts),
+    ("print", Arity::Variadic { min: 0 }, print),
+    ("input", Arity::Variadic { min: 0 }, input),
+    ("read_file", Arity::Fixed(1), read_file),
+    ("write_file", Arity::Fixed(2), write_file),
+    ("env", Arity::Fixed(1), env),
+    ("assert", Arity::Variadic { min: 1 }, assert),
+    ("assert_eq", Arity::Fixed(2), assert_eq),
+];
+
+fn write_stdout(ctx: &mut Ctx, text: &str) -> Result<(), RuntimeError> {
+    let output = ctx.output();
+    output
+        .write_all(text.as_bytes())
+        .and_then(|_| output.flush())
+        .map_err(|err| RuntimeError::new(format!("stdout: {}", err)))
+}
+
+// Prints each argument on its own line.
+fn puts(ctx: &mut Ctx, args: &[Object]) -> Result<Object, RuntimeError> {
+    IoPolicy::check(ctx.io_policy().stdout, "stdout")?;
+    let text: String = args.iter().map(|arg| format!("{}\n", arg)).collect();
+    write_stdout(ctx, &text)?;
+    Ok(Object::Null)
+}
+
+// Prints the arguments separated by spaces, without a newline.
+fn print(ctx: &mut Ctx, args: &[Object]) -> Result<Object, RuntimeError> {
+    IoPolicy::check(ctx.io_policy().stdout, "stdout")?;
+    let text = args
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect::<Vec<String>>()
+        .join(" ");
+    write_stdout(ctx, &text)?;
+    Ok(Object::Null)
+}
+
+// `input()` or `input(prompt)`. Reads a line without its line break, or
+// gives `null` at the end of the input.
+fn input(ctx: &mut Ctx, args: &[Object]) -> Result<Object, RuntimeError> {
+    if args.len() > 1 {
+        return Err(RuntimeError::new(format!(
+            "wrong number of arguments: want=0 or 1, got={}",
+            args.len()
+        )));
+    }
+    IoPolicy::check(ctx.io_policy().stdin, "stdin")?;
+    if let Some(prompt) = args.first() {
+        print(ctx, std::slice::from_ref(prompt))?;
+    }
+
+    let mut line = String::new();
+    let read = ctx
+        .read_line(&mut line)
+        .map_err(|err| RuntimeError::new(format!("stdin: {}", err)))?;
+    if read == 0 {
+        return Ok(Object::Null);
+    }
+    let line = line.strip_suffix('\n').unwrap_or(&line);
+    Ok(line.strip_suffix('\r').unwrap_or(line).into_object())
+}
+
+fn read_file(ctx: &mut Ctx, args: &[Object]) -> Result<Object, RuntimeError> {
+    let path = ctx.io_policy().readable(&String::from_object(&args[0])?)?;
+    let contents = fs::read_to_string(&path).map_err(|err| io_error(&path, err))?;
+    Ok(contents.into_object())
+}
+
+fn write_file(ctx: &mut Ctx, args: &[Object]) -> Result<Object, RuntimeError> {
+    let path = ctx.io_policy().writable(&String::from_object(&args[0])?)?;
+    let contents = String::from_object(&args[1])?;
+    // A new file is created exclusively, so a symbolic link put in its
+    // place after the path was checked is not followed.
+    let file = match OpenOptions::new().write(true).truncate(true).open(&path) {
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            OpenOptions::new().write(true).create_new(true).open(&path)
+        }
+        file => file,
+    };
+    file.and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|err| io_error(&path, err))?;
+    Ok(Object::Null)
+}
+
+// The value of an environment variable, or `null` when it is not set.
+fn env(ctx: &mut Ctx, args: &[Object]) -> Result<Object, RuntimeError> {
+    let name = String::from_object(&args[0])?;
+    IoPolicy::check(ctx.io_policy().env, &format!("env {}", name))?;
+    Ok(std::env::var(&name).ok().into_object())
+}
+
+// `assert(cond)` or `assert(cond, message)`. Fails unless `cond` is
+// truthy, the same way a condition of `if` is.
+fn assert(_ctx: &mut Ctx, args: &[Object]) -> Result<Object, RuntimeError> {
+    if args.len() > 2 {
+        return Err(RuntimeError::new(format!(
+            "wrong number of arguments: want=1 or 2, got={}",
+            args.len()
+        )));
+    }
+    match (&args[0], args.get(1)) {
+        (Object::Null | Object::Boolean(BooleanObject { value: false }), None) => {
+            Err(RuntimeError::new("assertion failed"))
+        }
+        (Object::Null | Object::Boolean(BooleanObject { value: false }), Some(message)) => {
+            Err(RuntimeError::new(format!("assertion failed: {}", message)))
+        }
+        _ => Ok(Object::Null),
+    }
+}
+
+// Unlike `==`, values of different types are simply not equal.
+fn assert_eq(_ctx: &mut Ctx, args: &[Object]) -> Result<Object, RuntimeError> {
+    let (left, right) = (&args[0], &args[1]);
+    let equal = match (left, right) {
+        (Object::Host(a), Object::Host(b)) => Rc::ptr_eq(a, b) || a.equals(b.as_ref()),
+        (a, b) => a.type_name() == b.type_name() && a.inspect() == b.inspect(),
+    };
+    if equal {
+        return Ok(Object::Null);
+    }
+    Err(RuntimeError::new(format!(
+        "assertion failed: left == right\n  left: {}\n right: {}",
+        left.inspect(),
+        right.inspect()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        evaluator::io::{IoPolicy, OutputBuffer},
+        utils, Error, Interpreter,
+    };
+    use std::{fs, path::PathBuf};
+
+    fn runtime_error(interpreter: &mut Interpreter, input: &str) -> String {
+        match interpreter.eval(input) {
+            Err(Error::Runtime(err)) => err.message,
+            other => panic!("expected a runtime error, got {:?}", other),
+        }
+    }
+
+    // A scratch directory with a directory `inside` to confine files to.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = utils::scratch_dir(name);
+        fs::create_dir(dir.join("inside")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_io_is_denied_by_default() {
+        let mut interpreter = Interpreter::new();
+        let tests = vec![
+            ("puts(1)", "permission denied: stdout"),
+            ("print(1)", "permission denied: stdout"),
+            ("input()", "permission denied: stdin"),
+            ("read_file(\"a.txt\")", "permission denied: read a.txt"),
+            (
+                "write_file(\"a.txt\", \"\")",
+                "permission denied: write a.txt",
+            ),
+            ("env(\"HOME\")", "permission denied: env HOME"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                runtime_error(&mut interpreter, input),
+                expected,
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_output_and_input_are_captured() {
+        let output = OutputBuffer::new();
+        let mut interpreter = Interpreter::new()
+            .with_io_policy(IoPolicy::allow_all())
+            .with_output(output.clone())
+            .with_input("Ada\r\nlast".as_bytes());
+
+        interpreter
+            .eval("puts(1, \"two\"); print(true, null); puts();")
+            .unwrap();
+        assert_eq!(output.contents(), "1\ntwo\ntrue null");
+
+        output.clear();
+        let input = "let name = input(\"name? \"); puts(\"hi \" + name); input(); input()";
+        assert_eq!(interpreter.eval(input).unwrap().to_string(), "null");
+        assert_eq!(output.contents(), "name? hi Ada\n");
+    }
+
+    #[test]
+    fn test_file_access_is_confined_to_root() {
+        let dir = scratch_dir("fs-root");
+        fs::write(dir.join("secret.txt"), "outside").unwrap();
+        let mut interpreter = Interpreter::new().with_io_policy(IoPolicy {
+            fs_read: true,
+            fs_write: true,
+            fs_root: Some(dir.join("inside")),
+            ..IoPolicy::deny_all()
+        });
+
+        interpreter
+            .eval("write_file(\"note.txt\", \"hello\");")
+            .unwrap();
+        assert_eq!(
+            interpreter
+                .eval("read_file(\"note.txt\")")
+                .unwrap()
+                .to_string(),
+            "hello"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("inside/note.txt")).unwrap(),
+            "hello"
+        );
+
+        let secret = dir.join("secret.txt");
+        let tests = vec![
+            (
+                String::from("read_file(\"../secret.txt\")"),
+                "permission denied: read ../secret.txt".to_string(),
+            ),
+            (
+                format!("read_file(\"{}\")", secret.display()),
+                format!("permission denied: read {}", secret.display()),
+            ),
+            (
+                String::from("write_file(\"../new.txt\", \"x\")"),
+                "permission denied: write ../new.txt".to_string(),
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(runtime_error(&mut interpreter, &input), expected);
+        }
+        assert!(!dir.join("new.txt").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dangling_symlink_does_not_escape_root() {
+        let dir = scratch_dir("fs-dangling");
+        let target = dir.join("escaped.txt");
+        std::os::unix::fs::symlink(&target, dir.join("inside/link.txt")).unwrap();
+        let mut interpreter = Interpreter::new().with_io_policy(IoPolicy {
+            fs_read: true,
+            fs_write: true,
+            fs_root: Some(dir.join("inside")),
+            ..IoPolicy::deny_all()
+        });
+
+        assert_eq!(
+            runtime_error(&mut interpreter, "write_file(\"link.txt\", \"x\")"),
+            "permission denied: write link.txt"
+        );
+        assert_eq!(
+            runtime_error(&mut interpreter, "read_file(\"link.txt\")"),
+            "permission denied: read link.txt"
+        );
+        assert!(!target.exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_only_policy() {
+        let dir = scratch_dir("fs-read-only");
+        fs::write(dir.join("inside/data.txt"), "42").unwrap();
+        let mut interpreter = Interpreter::new().with_io_policy(IoPolicy {
+            fs_read: true,
+            fs_root: Some(dir.join("inside")),
+            ..IoPolicy::deny_all()
+        });
+
+        assert_eq!(
+            interpreter
+                .eval("read_file(\"data.txt\")")
+                .unwrap()
+                .to_string(),
+            "42"
+        );
+        assert_eq!(
+            runtime_error(&mut interpreter, "write_file(\"data.txt\", \"0\")"),
+            "permission denied: write data.txt"
+        );
+        // a missing file inside the root is an ordinary I/O error
+        let err = runtime_error(&mut interpreter, "read_file(\"missing.txt\")");
+        assert!(!err.starts_with("permission denied"), "{}", err);
+        assert!(err.contains("missing.txt"), "{}", err);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_assertions() {
+        let mut interpreter = Interpreter::new();
+        let passing = vec![
+            "assert(true)",
+            "assert(0, \"zero is truthy\")",
+            "assert_eq(1 + 1, 2)",
+            "assert_eq(\"a\" + \"b\", \"ab\")",
+            "assert_eq(null, null)",
+        ];
+        for input in passing {
+            assert_eq!(
+                interpreter.eval(input).unwrap().to_string(),
+                "null",
+                "input: {}",
+                input
+            );
+        }
+
+        let tests = vec![
+            ("assert(false)", "assertion failed"),
+            ("assert(null, \"no value\")", "assertion failed: no value"),
+            (
+                "assert_eq(2, 3)",
+                "assertion failed: left == right\n  left: 2\n right: 3",
+            ),
+            (
+                "assert_eq(1, \"1\")",
+                "assertion failed: left == right\n  left: 1\n right: \"1\"",
+            ),
+            (
+                "assert(true, 1, 2)",
+                "wrong number of arguments: want=1 or 2, got=3",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                runtime_error(&mut interpreter, input),
+                expected,
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_env_access() {
+        let mut interpreter = Interpreter::new().with_io_policy(IoPolicy {
+            env: true,
+            ..IoPolicy::deny_all()
+        });
+        let expected = std::env::var("CARGO_PKG_NAME").unwrap();
+        assert_eq!(
+            interpreter
+                .eval("env(\"CARGO_PKG_NAME\")")
+                .unwrap()
+                .to_string(),
+            expected
+        );
+        assert_eq!(
+            interpreter
+                .eval("env(\"SURELY_NOT_SET_ANYWHERE\")")
+                .unwrap()
+                .to_string(),
+            "null"
+        );
+        assert_eq!(
+            runtime_error(&mut interpreter, "env(1)"),
+            "type mismatch: expected STRING, got INTEGER"
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use crate::evaluator::object::*;
+use std::{collections::HashMap, rc::Rc};
+
+/// Converts a value of the language into a Rust value, failing when the
+/// value has the wrong type.
+pub trait FromObject: Sized {
+    fn from_object(obj: &Object) -> Result<Self, RuntimeError>;
+}
+
+/// Converts a Rust value into a value of the language.
+pub trait IntoObject {
+    fn into_object(self) -> Object;
+}
+
+fn expected(type_name: &str, obj: &Object) -> RuntimeError {
+    RuntimeError::new(format!(
+        "type mismatch: expected {}, got {}",
+        type_name,
+        obj.type_name()
+    ))
+}
+
+impl FromObject for Object {
+    fn from_object(obj: &Object) -> Result<Self, RuntimeError> {
+        Ok(obj.clone())
+    }
+}
+
+impl FromObject for i64 {
+    fn from_object(obj: &Object) -> Result<Self, RuntimeError> {
+        match obj {
+            Object::Integer(v) => Ok(v.value.into()),
+            other => Err(expected("INTEGER", other)),
+        }
+    }
+}
+
+impl FromObject for bool {
+    fn from_object(obj: &Object) -> Result<Self, RuntimeError> {
+        match obj {
+            Object::Boolean(v) => Ok(v.value),
+            other => Err(expected("BOOLEAN", other)),
+        }
+    }
+}
+
+impl FromObject for String {
+    fn from_object(obj: &Object) -> Result<Self, RuntimeError> {
+        match obj {
+            Object::String(v) => Ok(v.value.clone()),
+            other => Err(expected("STRING", other)),
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for Vec<T> {
+    fn from_object(obj: &Object) -> Result<Self, RuntimeError> {
+        match obj {
+            Object::Array(v) => v.elements.iter().map(T::from_object).collect(),
+            other => Err(expected("ARRAY", other)),
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for HashMap<String, T> {
+    fn from_object(obj: &Object) -> Result<Self, RuntimeError> {
+        match obj {
+            Object::Hash(v) => v
+                .pairs
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), T::from_object(value)?)))
+                .collect(),
+            other => Err(expected("HASH", other)),
+        }
+    }
+}
+
+impl IntoObject for Object {
+    fn into_object(self) -> Object {
+        self
+    }
+}
+
+// Integers of the language are 32 bits wide; larger results are reported
+// the same way as an overflowing `+`.
+impl IntoObject for i64 {
+    fn into_object(self) -> Object {
+        match i32::try_from(self) {
+            Ok(value) => Object::Integer(IntegerObject { value }),
+            Err(_) => Object::Error(RuntimeError::new("integer overflow")),
+        }
+    }
+}
+
+impl IntoObject for bool {
+    fn into_object(self) -> Object {
+        Object::Boolean(if self { TRUE } else { FALSE })
+    }
+}
+
+impl IntoObject for String {
+    fn into_object(self) -> Object {
+        Object::String(StringObject { value: self })
+    }
+}
+
+impl IntoObject for &str {
+    fn into_object(self) -> Object {
+        self.to_string().into_object()
+    }
+}
+
+impl IntoObject for () {
+    fn into_object(self) -> Object {
+        Object::Null
+    }
+}
+
+impl<T: IntoObject> IntoObject for Option<T> {
+    fn into_object(self) -> Object {
+        self.map_or(Object::Null, T::into_object)
+    }
+}
+
+// An element that fails to convert fails the whole container, rather than
+// ending up inside it.
+fn converted<T: IntoObject>(value: T) -> Result<Object, RuntimeError> {
+    match value.into_object() {
+        Object::Error(err) => Err(err),
+        obj => Ok(obj),
+    }
+}
+
+impl<T: IntoObject> IntoObject for Vec<T> {
+    fn into_object(self) -> Object {
+        self.into_iter()
+            .map(converted)
+            .collect::<Result<_, _>>()
+            .map(|elements| Object::Array(ArrayObject { elements }))
+            .into_object()
+    }
+}
+
+impl<T: IntoObject> IntoObject for HashMap<String, T> {
+    fn into_object(self) -> Object {
+        self.into_iter()
+            .map(|(key, value)| Ok((key, converted(value)?)))
+            .collect::<Result<_, _>>()
+            .map(|pairs| Object::Hash(HashObject { pairs }))
+            .into_object()
+    }
+}
+
+// An `Err` becomes a runtime error of the program that made the call.
+impl<T: IntoObject> IntoObject for Result<T, RuntimeError> {
+    fn into_object(self) -> Object {
+        match self {
+            Ok(value) => value.into_object(),
+            Err(err) => Object::Error(err),
+        }
+    }
+}
+
+/// A plain Rust function or closure that can be registered with
+/// `Interpreter::register`. Implemented for functions of up to five
+/// arguments that implement `FromObject`, returning an `IntoObject`.
+pub trait HostFn<Args> {
+    fn arity(&self) -> Arity;
+    fn call(&self, args: &[Object]) -> Result<Object, RuntimeError>;
+}
+
+macro_rules! impl_host_fn {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg,)*> HostFn<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R,
+            R: IntoObject,
+            $($arg: FromObject,)*
+        {
+            fn arity(&self) -> Arity {
+                Arity::Fixed(<[&str]>::len(&[$(stringify!($arg)),*]))
+            }
+
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call(&self, args: &[Object]) -> Result<Object, RuntimeError> {
+                let mut args = args.iter();
+                $(let $arg = $arg::from_object(args.next().unwrap_or(&Object::Null))?;)*
+                match (self)($($arg),*).into_object() {
+                    Object::Error(err) => Err(err),
+                    value => Ok(value),
+                }
+            }
+        }
+    };
+}
+
+impl_host_fn!();
+impl_host_fn!(A);
+impl_host_fn!(A, B);
+impl_host_fn!(A, B, C);
+impl_host_fn!(A, B, C, D);
+impl_host_fn!(A, B, C, D, E);
+
+pub(super) fn builtin<T: Into<String>>(name: T, arity: Arity, func: Rc<NativeFn>) -> Object {
+    Object::Builtin(BuiltinObject {
+        name: name.into(),
+        arity,
+        func,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<T: FromObject + IntoObject + Clone + PartialEq + std::fmt::Debug>(value: T) {
+        let obj = value.clone().into_object();
+        assert_eq!(T::from_object(&obj).unwrap(), value);
+    }
+
+    #[test]
+    fn test_conversions_round_trip() {
+        round_trip(42i64);
+        round_trip(-7i64);
+        round_trip(true);
+        round_trip(String::from("hi \"there\""));
+        round_trip(vec![1i64, 2, 3]);
+        round_trip(vec![vec![true], vec![]]);
+        round_trip(HashMap::from([
+            (String::from("a"), 1i64),
+            (String::from("b"), 2i64),
+        ]));
+    }
+
+    #[test]
+    fn test_conversion_errors() {
+        let err = i64::from_object(&true.into_object()).unwrap_err();
+        assert_eq!(err.message, "type mismatch: expected INTEGER, got BOOLEAN");
+
+        let err = Vec::<bool>::from_object(&vec![1i64].into_object()).unwrap_err();
+        assert_eq!(err.message, "type mismatch: expected BOOLEAN, got INTEGER");
+
+        let Object::Error(err) = i64::MAX.into_object() else {
+            panic!("expected an overflow error");
+        };
+        assert_eq!(err.message, "integer overflow");
+
+        let Object::Error(err) = vec![vec![1, i64::MAX]].into_object() else {
+            panic!("expected an overflow error");
+        };
+        assert_eq!(err.message, "integer overflow");
+        let hash = HashMap::from([(String::from("big"), i64::MAX)]);
+        let Object::Error(err) = hash.into_object() else {
+            panic!("expected an overflow error");
+        };
+        assert_eq!(err.message, "integer overflow");
+    }
+
+    #[test]
+    fn test_converted_values_display() {
+        assert_eq!(vec!["a", "b"].into_object().to_string(), "[\"a\", \"b\"]");
+        let hash = HashMap::from([(String::from("k"), vec![1i64])]);
+        assert_eq!(hash.into_object().to_string(), "{\"k\": [1]}");
+        assert_eq!(().into_object().to_string(), "null");
+        assert_eq!(String::from("plain").into_object().to_string(), "plain");
+    }
+}
@@ -0,0 +1,757 @@
+mod builtins;
+pub mod host;
+
+use crate::{
+    evaluator::{
+        context::{Ctx, Limits, ResourceExhausted, TestCase},
+        debugger::Debugger,
+        environment::Environment,
+        eval_with,
+        io::IoPolicy,
+        object::{Arity, Frame, Object, RuntimeError},
+        resolver::Resolver,
+    },
+    lexer::Lexer,
+    optimize::{optimize, OptLevel},
+    parser::Parser,
+};
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{BufRead, Write},
+    rc::Rc,
+};
+
+/// A value produced by or handed to a program.
+pub type Value = Object;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The input is not a valid program.
+    Parse(Vec<String>),
+    /// The program refers to names that are never declared.
+    Resolve(Vec<String>),
+    /// Evaluation failed, e.g. on a type mismatch or a division by zero.
+    Runtime(RuntimeError),
+    /// Evaluation was stopped by one of the interpreter's `Limits`.
+    ResourceExhausted(ResourceExhausted),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(errors) | Error::Resolve(errors) => write!(f, "{}", errors.join("\n")),
+            Error::Runtime(RuntimeError {
+                message,
+                line: Some(line),
+                ..
+            }) => write!(f, "line {}: {}", line, message),
+            Error::Runtime(e) => write!(f, "{}", e),
+            Error::ResourceExhausted(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    /// The calls a runtime error came out of, innermost first.
+    pub fn backtrace(&self) -> &[Frame] {
+        match self {
+            Error::Runtime(e) => &e.backtrace,
+            _ => &[],
+        }
+    }
+}
+
+/// An interpreter that keeps its global environment between calls to
+/// `eval`, so a host can feed a program in several pieces the way the
+/// REPL does.
+///
+/// ```
+/// use interpreter_rust::Interpreter;
+///
+/// let mut interpreter = Interpreter::new();
+/// interpreter.eval("let double = fn(x) { x * 2 };").unwrap();
+/// let value = interpreter.eval("double(21)").unwrap();
+/// assert_eq!(value.to_string(), "42");
+/// ```
+pub struct Interpreter {
+    env: Rc<RefCell<Environment>>,
+    ctx: Ctx,
+    opt_level: OptLevel,
+}
+
+impl Interpreter {
+    /// Creates an interpreter with the builtin functions bound and all
+    /// I/O denied; see `with_io_policy`.
+    pub fn new() -> Self {
+        let mut interpreter = Interpreter {
+            env: Environment::new(),
+            ctx: Ctx::new(),
+            opt_level: OptLevel::default(),
+        };
+        interpreter.ctx.track(&interpreter.env);
+        for (name, arity, func) in builtins::BUILTINS {
+            interpreter.register_fn(name, *arity, func);
+        }
+        interpreter
+    }
+
+    pub fn with_opt_level(mut self, opt_level: OptLevel) -> Self {
+        self.opt_level = opt_level;
+        self
+    }
+
+    /// Bounds what each evaluation may use. A program that exceeds a
+    /// limit fails with `Error::ResourceExhausted`.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.ctx.set_limits(limits);
+        self
+    }
+
+    /// Grants programs the I/O allowed by `policy`. Builtins doing
+    /// anything else fail with a `permission denied` runtime error.
+    pub fn with_io_policy(mut self, policy: IoPolicy) -> Self {
+        self.ctx.set_io_policy(policy);
+        self
+    }
+
+    /// Sends what `puts` and `print` write to `output` instead of stdout.
+    pub fn with_output<W: Write + 'static>(mut self, output: W) -> Self {
+        self.ctx.set_output(Box::new(output));
+        self
+    }
+
+    /// Logs every node evaluated, every function call and every binding
+    /// to `output`, for debugging programs. Evaluation is slower while
+    /// tracing.
+    pub fn with_trace<W: Write + 'static>(mut self, output: W) -> Self {
+        self.ctx.set_trace(Box::new(output));
+        self
+    }
+
+    /// Lets `debugger` pause programs before their statements.
+    pub fn with_debugger(mut self, debugger: Debugger) -> Self {
+        self.ctx.set_debugger(debugger);
+        self
+    }
+
+    /// Makes `input` read lines from `input` instead of stdin.
+    pub fn with_input<R: BufRead + 'static>(mut self, input: R) -> Self {
+        self.ctx.set_input(Box::new(input));
+        self
+    }
+
+    /// Parses, resolves and evaluates `input` in the global environment.
+    /// Bindings made by `input` stay visible to later calls.
+    pub fn eval(&mut self, input: &str) -> Result<Value, Error> {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let mut prog = parser.parse_program();
+        if !parser.errors.is_empty() {
+            return Err(Error::Parse(parser.errors));
+        }
+
+        let mut resolver = Resolver::new(&self.env);
+        resolver.resolve_program(&mut prog);
+        if !resolver.errors.is_empty() {
+            return Err(Error::Resolve(resolver.errors));
+        }
+
+        let prog = optimize(prog, self.opt_level);
+        self.ctx.start();
+        let result = eval_with(&prog, Rc::clone(&self.env), &mut self.ctx);
+        self.finish(result)
+    }
+
+    /// The `test` declarations evaluated since the last call, in the
+    /// order they were met.
+    pub fn take_tests(&mut self) -> Vec<TestCase> {
+        self.ctx.take_tests()
+    }
+
+    /// Runs the body of `test` in a scope of its own. A failed `assert`
+    /// comes back as `Error::Runtime`.
+    pub fn run_test(&mut self, test: &TestCase) -> Result<(), Error> {
+        self.ctx.start();
+        let env = Environment::new_extended(Rc::clone(&test.env));
+        let result = eval_with(&test.body, env, &mut self.ctx);
+        self.finish(result).map(|_| ())
+    }
+
+    /// Evaluation steps the last evaluation took, one per node evaluated,
+    /// whether it succeeded or not.
+    pub fn steps(&self) -> u64 {
+        self.ctx.steps()
+    }
+
+    fn finish(&mut self, result: Option<Object>) -> Result<Value, Error> {
+        if let Some(resource) = self.ctx.take_exhausted() {
+            return Err(Error::ResourceExhausted(resource));
+        }
+        match result {
+            Some(Object::Error(e)) => Err(Error::Runtime(e)),
+            Some(value) => Ok(value),
+            None => Ok(Object::Null),
+        }
+    }
+
+    /// Returns the value bound to `name` in the global environment.
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.env
+            .borrow()
+            .get(name)
+            .map(|v| v.as_ref().borrow().clone())
+    }
+
+    /// Binds `name` in the global environment, replacing any previous
+    /// binding. Programs evaluated afterwards can refer to it.
+    pub fn set_global<T: Into<String>>(&mut self, name: T, value: Value) {
+        self.env.borrow_mut().insert(name.into(), value);
+    }
+
+    /// Binds `name` to a host function that receives its arguments as
+    /// language values. The number of arguments is checked against
+    /// `arity` before `func` is called, and an `Err` it returns becomes a
+    /// runtime error of the calling program.
+    pub fn register_fn<F>(&mut self, name: &str, arity: Arity, func: F)
+    where
+        F: Fn(&mut Ctx, &[Object]) -> Result<Object, RuntimeError> + 'static,
+    {
+        self.set_global(name, host::builtin(name, arity, Rc::new(func)));
+    }
+
+    /// Binds `name` to a plain Rust function, converting its arguments
+    /// and result with `FromObject` and `IntoObject`.
+    ///
+    /// ```
+    /// use interpreter_rust::Interpreter;
+    ///
+    /// fn add(a: i64, b: i64) -> i64 {
+    ///     a + b
+    /// }
+    ///
+    /// let mut interpreter = Interpreter::new();
+    /// interpreter.register("add", add);
+    /// assert_eq!(interpreter.eval("add(1, 2)").unwrap().to_string(), "3");
+    /// ```
+    pub fn register<Args, F>(&mut self, name: &str, func: F)
+    where
+        F: host::HostFn<Args> + 'static,
+    {
+        let arity = func.arity();
+        self.register_fn(name, arity, move |_, args| func.call(args));
+    }
+
+    /// The global environment programs are evaluated in.
+    pub fn env(&self) -> &Rc<RefCell<Environment>> {
+        &self.env
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::object::{HostObject, IntegerObject};
+    use host::{FromObject, IntoObject};
+    use std::collections::HashMap;
+
+    fn int(value: i32) -> Value {
+        Object::Integer(IntegerObject { value })
+    }
+
+    #[test]
+    fn test_state_persists_between_evals() {
+        let mut interpreter = Interpreter::new();
+        let inputs = vec![
+            ("let a = 5;", "null"),
+            ("let add = fn(x, y) { x + y };", "null"),
+            ("add(a, 10)", "15"),
+            ("let a = add(a, 1);", "null"),
+            ("a", "6"),
+        ];
+
+        for (input, expected) in inputs {
+            let value = interpreter.eval(input).expect("input should evaluate");
+            assert_eq!(value.to_string(), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_errors_are_distinguished() {
+        let mut interpreter = Interpreter::new();
+
+        let Err(Error::Parse(errors)) = interpreter.eval("let = 1;") else {
+            panic!("expected Error::Parse");
+        };
+        assert!(!errors.is_empty());
+
+        assert_eq!(
+            interpreter.eval("missing + 1").unwrap_err(),
+            Error::Resolve(vec![String::from("line 1: identifier not found: missing")])
+        );
+
+        assert_eq!(
+            interpreter.eval("1 + true").unwrap_err(),
+            Error::Runtime(RuntimeError {
+                line: Some(1),
+                ..RuntimeError::new("type mismatch: INTEGER + BOOLEAN")
+            })
+        );
+
+        // a failed evaluation leaves the interpreter usable
+        assert_eq!(interpreter.eval("1 + 1").unwrap().to_string(), "2");
+    }
+
+    #[test]
+    fn test_get_and_set_globals() {
+        let mut interpreter = Interpreter::new();
+        assert!(interpreter.get_global("limit").is_none());
+
+        interpreter.set_global("limit", int(10));
+        interpreter.eval("let twice = limit * 2;").unwrap();
+        assert_eq!(interpreter.get_global("twice").unwrap().to_string(), "20");
+
+        interpreter.set_global("limit", int(1));
+        assert_eq!(interpreter.eval("limit + twice").unwrap().to_string(), "21");
+    }
+
+    #[test]
+    fn test_register_typed_functions() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register("add", |a: i64, b: i64| a + b);
+        interpreter.register("shout", |s: String| s.to_uppercase() + "!");
+        interpreter.register("sum", |v: Vec<i64>| v.iter().sum::<i64>());
+        interpreter.register("keys", |h: HashMap<String, bool>| {
+            let mut keys: Vec<String> = h.into_keys().collect();
+            keys.sort();
+            keys
+        });
+        interpreter.register("pi", || 3i64);
+        interpreter.set_global("nums", vec![1i64, 2, 3].into_object());
+        interpreter.set_global(
+            "flags",
+            HashMap::from([(String::from("b"), true), (String::from("a"), false)]).into_object(),
+        );
+
+        let tests = vec![
+            ("add(1, add(2, 3))", "6"),
+            ("shout(\"hi\" + \" there\")", "HI THERE!"),
+            ("sum(nums)", "6"),
+            ("keys(flags)", "[\"a\", \"b\"]"),
+            ("pi() * 2", "6"),
+            (
+                "let twice = fn(f, x) { f(f(x, x), x) }; twice(add, 5)",
+                "15",
+            ),
+            ("add", "<builtin add>"),
+        ];
+        for (input, expected) in tests {
+            let value = interpreter.eval(input).expect("input should evaluate");
+            assert_eq!(value.to_string(), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_host_function_errors() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register("add", |a: i64, b: i64| a + b);
+        interpreter.register("div", |a: i64, b: i64| {
+            a.checked_div(b)
+                .ok_or_else(|| RuntimeError::new("cannot divide by zero"))
+        });
+        interpreter.register("big", || vec![i64::MAX]);
+
+        let in_f = vec![Frame {
+            function: String::from("f"),
+            line: Some(1),
+            args: vec![],
+        }];
+        let tests = vec![
+            ("add(1)", "wrong number of arguments: want=2, got=1", vec![]),
+            (
+                "add(1, true)",
+                "type mismatch: expected INTEGER, got BOOLEAN",
+                vec![],
+            ),
+            ("div(1, 0)", "cannot divide by zero", vec![]),
+            ("add(2147483647, 1)", "integer overflow", vec![]),
+            ("big()", "integer overflow", vec![]),
+            (
+                "let f = fn() { div(1, 0); 5 }; f()",
+                "cannot divide by zero",
+                in_f,
+            ),
+        ];
+        for (input, expected, backtrace) in tests {
+            assert_eq!(
+                interpreter.eval(input).unwrap_err(),
+                Error::Runtime(RuntimeError {
+                    line: Some(1),
+                    backtrace,
+                    ..RuntimeError::new(expected)
+                }),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Point {
+        x: i64,
+        y: i64,
+    }
+
+    impl fmt::Display for Point {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "Point({}, {})", self.x, self.y)
+        }
+    }
+
+    impl HostObject for Point {
+        fn type_name(&self) -> &'static str {
+            "POINT"
+        }
+
+        fn get(&self, name: &str) -> Option<Object> {
+            match name {
+                "x" => Some(self.x.into_object()),
+                "y" => Some(self.y.into_object()),
+                _ => None,
+            }
+        }
+
+        fn call_method(
+            &self,
+            ctx: &mut Ctx,
+            name: &str,
+            args: &[Object],
+        ) -> Option<Result<Object, RuntimeError>> {
+            let result = match (name, args) {
+                ("scale", [k]) => i64::from_object(k).map(|k| point(self.x * k, self.y * k)),
+                ("map", [f]) => (|| {
+                    let x = i64::from_object(&ctx.call(f, vec![self.x.into_object()])?)?;
+                    let y = i64::from_object(&ctx.call(f, vec![self.y.into_object()])?)?;
+                    Ok(point(x, y))
+                })(),
+                ("scale" | "map", _) => Err(RuntimeError::new("wrong number of arguments")),
+                _ => return None,
+            };
+            Some(result)
+        }
+
+        fn equals(&self, other: &dyn HostObject) -> bool {
+            (other as &dyn std::any::Any).downcast_ref::<Point>() == Some(self)
+        }
+    }
+
+    fn point(x: i64, y: i64) -> Object {
+        Object::Host(Rc::new(Point { x, y }))
+    }
+
+    #[test]
+    fn test_host_objects() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("p", point(1, 2));
+        interpreter.register_fn("point", Arity::Fixed(2), |_, args| {
+            Ok(point(
+                i64::from_object(&args[0])?,
+                i64::from_object(&args[1])?,
+            ))
+        });
+
+        let tests = vec![
+            ("p", "Point(1, 2)"),
+            ("p.x + p.y * 10", "21"),
+            ("p.scale(3)", "Point(3, 6)"),
+            ("p.scale(3).y", "6"),
+            ("p.map(fn(v) { v - 1 })", "Point(0, 1)"),
+            ("let f = fn(q) { q.x }; f(point(7, 8))", "7"),
+            ("p == p", "true"),
+            ("p == point(1, 2)", "true"),
+            ("p != point(2, 1)", "true"),
+            ("let q = p; q == p", "true"),
+        ];
+        for (input, expected) in tests {
+            let value = interpreter.eval(input).expect("input should evaluate");
+            assert_eq!(value.to_string(), expected, "input: {}", input);
+        }
+
+        let errors = vec![
+            ("p.z", "unknown property: POINT.z"),
+            ("p.norm()", "unknown method: POINT.norm"),
+            (
+                "p.scale(true)",
+                "type mismatch: expected INTEGER, got BOOLEAN",
+            ),
+            ("p.x.y", "unknown property: INTEGER.y"),
+            ("p + p", "unknown operator: POINT + POINT"),
+            ("p == 1", "type mismatch: POINT == INTEGER"),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(
+                interpreter.eval(input).unwrap_err(),
+                Error::Runtime(RuntimeError {
+                    line: Some(1),
+                    ..RuntimeError::new(expected)
+                }),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_runtime_errors_carry_backtrace() {
+        let mut interpreter = Interpreter::new();
+        let input = "
+let div = fn(a, b) {
+    a / b
+};
+let ratio = div;
+let calc = fn(x) { ratio(x, x - 1) };
+fn(f) { f(1) }(calc)";
+        let frame = |function: &str, line: usize, args: &[&str]| Frame {
+            function: function.to_string(),
+            line: Some(line),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        };
+        assert_eq!(
+            interpreter.eval(input).unwrap_err(),
+            Error::Runtime(RuntimeError {
+                line: Some(3),
+                backtrace: vec![
+                    frame("div", 6, &["1", "0"]),
+                    frame("calc", 7, &["1"]),
+                    frame("<anonymous>", 7, &["fn(x) { ... }"]),
+                ],
+                ..RuntimeError::new("division by zero")
+            })
+        );
+
+        // errors outside functions and in builtins have no frames
+        let e = interpreter.eval("1 + true").unwrap_err();
+        assert!(e.backtrace().is_empty());
+        let e = interpreter.eval("assert(false)").unwrap_err();
+        assert!(e.backtrace().is_empty());
+        // the frames of a failed evaluation are not left behind
+        let e = interpreter.eval("calc(1)").unwrap_err();
+        assert_eq!(e.backtrace().len(), 2);
+        // a frame shows the arguments as they were passed
+        let e = interpreter
+            .eval("let shift = fn(n) { let n = n + 1; n / 0 }; shift(1)")
+            .unwrap_err();
+        assert_eq!(e.backtrace(), [frame("shift", 1, &["1"])]);
+    }
+
+    #[test]
+    fn test_run_declared_tests() {
+        let mut interpreter = Interpreter::new();
+        let input = "
+let double = fn(x) { x * 2 };
+test \"doubles\" { assert_eq(double(2), 4) }
+test \"fails\" {
+    let x = double(1);
+    assert_eq(x, 3);
+}
+test \"returns early\" { return 1; assert(false) }";
+        // declaring a test does not run it
+        assert_eq!(interpreter.eval(input).unwrap().to_string(), "null");
+
+        let tests = interpreter.take_tests();
+        let names: Vec<&str> = tests.iter().map(|test| test.name.as_str()).collect();
+        assert_eq!(names, vec!["doubles", "fails", "returns early"]);
+        assert_eq!(tests[1].line, 4);
+
+        let results: Vec<Result<(), Error>> = tests
+            .iter()
+            .map(|test| interpreter.run_test(test))
+            .collect();
+        assert_eq!(results[0], Ok(()));
+        assert_eq!(
+            results[1],
+            Err(Error::Runtime(RuntimeError {
+                line: Some(6),
+                ..RuntimeError::new("assertion failed: left == right\n  left: 2\n right: 3")
+            }))
+        );
+        assert_eq!(results[2], Ok(()));
+
+        // bindings made inside a test stay inside it
+        assert_eq!(interpreter.get_global("x").map(|v| v.to_string()), None);
+        assert!(interpreter.take_tests().is_empty());
+    }
+
+    #[test]
+    fn test_register_variadic_function_with_callback() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_fn("max", Arity::Variadic { min: 1 }, |_, args| {
+            let mut max = i64::from_object(&args[0])?;
+            for arg in &args[1..] {
+                max = max.max(i64::from_object(arg)?);
+            }
+            Ok(max.into_object())
+        });
+        interpreter.register_fn("apply", Arity::Variadic { min: 1 }, |ctx, args| {
+            ctx.call(&args[0], args[1..].to_vec())
+        });
+
+        let tests = vec![
+            ("max(3)", "3"),
+            ("max(3, 9, -1)", "9"),
+            ("apply(fn(x, y) { x - y }, 10, 4)", "6"),
+            ("apply(max, 1, 2)", "2"),
+            ("apply(fn() { return 7; })", "7"),
+        ];
+        for (input, expected) in tests {
+            let value = interpreter.eval(input).expect("input should evaluate");
+            assert_eq!(value.to_string(), expected, "input: {}", input);
+        }
+
+        assert_eq!(
+            interpreter.eval("max()").unwrap_err(),
+            Error::Runtime(RuntimeError {
+                line: Some(1),
+                ..RuntimeError::new("wrong number of arguments: want=1+, got=0")
+            })
+        );
+    }
+
+    fn exhausted(interpreter: &mut Interpreter, input: &str) -> ResourceExhausted {
+        match interpreter.eval(input) {
+            Err(Error::ResourceExhausted(resource)) => resource,
+            other => panic!("expected Error::ResourceExhausted, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_fuel_stops_infinite_loop() {
+        let mut interpreter = Interpreter::new().with_limits(Limits {
+            fuel: Some(10_000),
+            ..Limits::default()
+        });
+        assert_eq!(
+            exhausted(&mut interpreter, "while (true) {}"),
+            ResourceExhausted::Fuel
+        );
+
+        // every evaluation gets a full tank and the globals survive
+        interpreter.eval("let a = 20;").unwrap();
+        assert_eq!(interpreter.eval("a + 1").unwrap().to_string(), "21");
+        assert_eq!(
+            exhausted(&mut interpreter, "while (a) {}"),
+            ResourceExhausted::Fuel
+        );
+    }
+
+    #[test]
+    fn test_steps_are_counted_per_evaluation() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval("let f = fn(x) { x };").unwrap();
+        interpreter.eval("f(1)").unwrap();
+        let call = interpreter.steps();
+        interpreter.eval("f(1); f(1)").unwrap();
+        // both programs take one step for the program node itself
+        assert_eq!(interpreter.steps(), 2 * call - 1);
+
+        let mut interpreter = Interpreter::new().with_limits(Limits {
+            fuel: Some(500),
+            ..Limits::default()
+        });
+        exhausted(&mut interpreter, "while (true) {}");
+        assert_eq!(interpreter.steps(), 500);
+    }
+
+    #[test]
+    fn test_timeout_stops_infinite_loop() {
+        let mut interpreter = Interpreter::new().with_limits(Limits {
+            timeout: Some(std::time::Duration::from_millis(20)),
+            ..Limits::default()
+        });
+        assert_eq!(
+            exhausted(&mut interpreter, "while (true) {}"),
+            ResourceExhausted::Time
+        );
+    }
+
+    #[test]
+    fn test_runaway_recursion_is_stopped() {
+        let input = "let f = fn(x) { f(x + 1) }; f(0)";
+
+        let mut interpreter = Interpreter::new().with_limits(Limits {
+            max_depth: Some(100),
+            ..Limits::default()
+        });
+        assert_eq!(exhausted(&mut interpreter, input), ResourceExhausted::Stack);
+
+        let mut interpreter = Interpreter::new().with_limits(Limits {
+            memory: Some(200),
+            ..Limits::default()
+        });
+        assert_eq!(
+            exhausted(&mut interpreter, input),
+            ResourceExhausted::Memory
+        );
+    }
+
+    #[test]
+    fn test_runaway_strings_are_stopped() {
+        let mut interpreter = Interpreter::new().with_limits(Limits {
+            memory: Some(1_000),
+            fuel: Some(2_000),
+            ..Limits::default()
+        });
+        let input = "
+            let s = \"xx\"; let n = 0;
+            while (n < 26) { let s = s + s; let n = n + 1; }
+            s";
+        assert_eq!(
+            exhausted(&mut interpreter, input),
+            ResourceExhausted::Memory
+        );
+
+        // values coming from the host are counted too
+        interpreter.register("big", || "x".repeat(2_000));
+        assert_eq!(
+            exhausted(&mut interpreter, "big()"),
+            ResourceExhausted::Memory
+        );
+    }
+
+    #[test]
+    fn test_memory_is_released_after_calls() {
+        let mut interpreter = Interpreter::new().with_limits(Limits {
+            memory: Some(200),
+            max_depth: Some(100),
+            ..Limits::default()
+        });
+        let input = "
+            let f = fn(n) { if (n > 0) { let m = n - 1; f(m) } else { n } };
+            f(50); f(50); f(50)";
+        assert_eq!(interpreter.eval(input).unwrap().to_string(), "0");
+    }
+
+    #[test]
+    fn test_host_cannot_swallow_exhaustion() {
+        let mut interpreter = Interpreter::new().with_limits(Limits {
+            fuel: Some(1_000),
+            ..Limits::default()
+        });
+        interpreter.register_fn("ignore", Arity::Fixed(1), |ctx, args| {
+            let _ = ctx.call(&args[0], vec![]);
+            Ok(Object::Null)
+        });
+        assert_eq!(
+            exhausted(&mut interpreter, "ignore(fn() { while (true) {} }); 1"),
+            ResourceExhausted::Fuel
+        );
+    }
+}
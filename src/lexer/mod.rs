@@ -6,6 +6,7 @@ pub struct Lexer {
     pub ch: char,
     pub position: usize,
     pub read_position: usize,
+    pub line: usize,
 }
 
 impl Lexer {
@@ -15,6 +16,7 @@ impl Lexer {
             ch: 0 as char,
             position: 0,
             read_position: 0,
+            line: 1,
         };
         l.read_char();
         return l;
@@ -30,9 +32,16 @@ impl Lexer {
             '+' => self.new_token(token::PLUS, self.ch.encode_utf8(&mut tmp)),
             '-' => self.new_token(token::MINUS, self.ch.encode_utf8(&mut tmp)),
             '*' => self.new_token(token::ASTERISK, self.ch.encode_utf8(&mut tmp)),
-            '/' => self.new_token(token::SLASH, self.ch.encode_utf8(&mut tmp)),
+            '/' => {
+                if self.peek_char_is('/') {
+                    let comment = self.read_comment();
+                    return self.new_token(token::COMMENT, &comment);
+                }
+                self.new_token(token::SLASH, self.ch.encode_utf8(&mut tmp))
+            }
             ';' => self.new_token(token::SEMICOLON, self.ch.encode_utf8(&mut tmp)),
             ',' => self.new_token(token::COMMA, self.ch.encode_utf8(&mut tmp)),
+            '.' => self.new_token(token::DOT, self.ch.encode_utf8(&mut tmp)),
             '(' => self.new_token(token::LPAREN, self.ch.encode_utf8(&mut tmp)),
             ')' => self.new_token(token::RPAREN, self.ch.encode_utf8(&mut tmp)),
             '{' => self.new_token(token::LBRACE, self.ch.encode_utf8(&mut tmp)),
@@ -40,6 +49,22 @@ impl Lexer {
             '<' => self.new_token(token::LT, self.ch.encode_utf8(&mut tmp)),
             '>' => self.new_token(token::GT, self.ch.encode_utf8(&mut tmp)),
             '\0' => self.new_token(token::EOF, self.ch.encode_utf8(&mut tmp)),
+            '"' => {
+                // a string may span lines, the token starts on the first
+                let line = self.line;
+                match self.read_string() {
+                    Ok(string) => token::Token {
+                        line,
+                        ..token::Token::new(token::STRING, &string)
+                    },
+                    Err(raw) => {
+                        return token::Token {
+                            line,
+                            ..token::Token::new(token::ILLEGAL, &raw)
+                        }
+                    }
+                }
+            }
             '=' => {
                 if self.peek_char_is('=') {
                     self.read_char();
@@ -65,7 +90,7 @@ impl Lexer {
                     let number = self.read_number();
                     return self.new_token(token::INT, &number);
                 }
-                token::Token::new(token::ILLEGAL, ('\0' as char).encode_utf8(&mut tmp))
+                self.new_token(token::ILLEGAL, self.ch.encode_utf8(&mut tmp))
             }
         };
         self.read_char();
@@ -98,12 +123,62 @@ impl Lexer {
         self.input[pos..self.position].to_owned()
     }
 
+    // Reads a `//` comment up to, but not including, the end of the line.
+    fn read_comment(&mut self) -> String {
+        let pos = self.position;
+        while self.ch != '\n' && self.ch != '\0' {
+            self.read_char();
+        }
+        self.input[pos..self.position].trim_end().to_owned()
+    }
+
+    // Reads a string literal and decodes its escapes, leaving the lexer on
+    // the closing quote. An unterminated string gives back its raw text.
+    fn read_string(&mut self) -> Result<String, String> {
+        let pos = self.position + 1;
+        loop {
+            self.read_char();
+            match self.ch {
+                '"' => break,
+                '\0' => return Err(self.input[pos - 1..].to_owned()),
+                '\\' => {
+                    // the escaped character can be a line break too
+                    self.read_char();
+                    if self.ch == '\n' {
+                        self.line += 1;
+                    }
+                }
+                '\n' => self.line += 1,
+                _ => {}
+            }
+        }
+
+        let mut string = String::new();
+        let mut chars = self.input[pos..self.position].chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                string.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('t') => string.push('\t'),
+                Some(c) => string.push(c),
+                None => {}
+            }
+        }
+        Ok(string)
+    }
+
     fn new_token(&self, token_type: token::TokenType, literal: &str) -> token::Token {
-        token::Token::new(token_type, literal)
+        token::Token {
+            line: self.line,
+            ..token::Token::new(token_type, literal)
+        }
     }
 
     fn peek_char(&self) -> char {
-        if self.read_position <= self.input.len() {
+        if self.read_position < self.input.len() {
             return self.input.as_bytes()[self.read_position] as char;
         }
         return '\0';
@@ -123,6 +198,9 @@ impl Lexer {
 
     fn skip_whitespace(&mut self) {
         while self.ch == ' ' || self.ch == '\t' || self.ch == '\n' || self.ch == '\r' {
+            if self.ch == '\n' {
+                self.line += 1;
+            }
             self.read_char();
         }
     }
@@ -249,4 +327,60 @@ mod test {
             assert_eq!(tt.literal, tk.literal);
         }
     }
+
+    #[test]
+    fn test_lexer_comments_and_lines() {
+        let input = "let a = 1; // one
+        // two
+        a / 2;";
+
+        let tests = vec![
+            (token::LET, "let", 1),
+            (token::IDENTIFIER, "a", 1),
+            (token::ASSIGN, "=", 1),
+            (token::INT, "1", 1),
+            (token::SEMICOLON, ";", 1),
+            (token::COMMENT, "// one", 1),
+            (token::COMMENT, "// two", 2),
+            (token::IDENTIFIER, "a", 3),
+            (token::SLASH, "/", 3),
+            (token::INT, "2", 3),
+            (token::SEMICOLON, ";", 3),
+        ];
+
+        let mut lexer = Lexer::new(input);
+
+        for (token_type, literal, line) in tests {
+            let tk = lexer.next_token();
+            assert_eq!(tk.token_type, token_type);
+            assert_eq!(tk.literal, literal);
+            assert_eq!(tk.line, line, "line of {}", literal);
+        }
+    }
+
+    #[test]
+    fn test_lexer_strings() {
+        let input = "\"hello\" \"a \\\"b\\\"\\n\" \"two
+lines\" \"escaped\\
+break\" x \"open";
+
+        let tests = vec![
+            (token::STRING, "hello", 1),
+            (token::STRING, "a \"b\"\n", 1),
+            (token::STRING, "two\nlines", 1),
+            (token::STRING, "escaped\nbreak", 2),
+            (token::IDENTIFIER, "x", 3),
+            (token::ILLEGAL, "\"open", 3),
+            (token::EOF, "\0", 3),
+        ];
+
+        let mut lexer = Lexer::new(input);
+
+        for (token_type, literal, line) in tests {
+            let tk = lexer.next_token();
+            assert_eq!(tk.token_type, token_type);
+            assert_eq!(tk.literal, literal);
+            assert_eq!(tk.line, line, "line of {}", literal);
+        }
+    }
 }
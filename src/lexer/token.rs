@@ -6,14 +6,19 @@ pub const EOF: &str = "EOF";
 pub const IDENTIFIER: &str = "IDENTIFIER";
 pub const LET: &str = "LET";
 pub const INT: &str = "INT";
+pub const STRING: &str = "STRING";
 pub const FUNCTION: &str = "FN";
 pub const RETURN: &str = "RETURN";
 pub const IF: &str = "IF";
 pub const ELSE: &str = "ELSE";
+pub const WHILE: &str = "WHILE";
 pub const TRUE: &str = "TRUE";
 pub const FALSE: &str = "FALSE";
+pub const NULL: &str = "NULL";
+pub const COMMENT: &str = "COMMENT";
 
 pub const COMMA: &str = ",";
+pub const DOT: &str = ".";
 pub const SEMICOLON: &str = ";";
 pub const LPAREN: &str = "(";
 pub const RPAREN: &str = ")";
@@ -34,6 +39,8 @@ pub const NOTEQ: &str = "!=";
 pub struct Token {
     pub token_type: TokenType,
     pub literal: String,
+    // 1-based line the token starts on, 0 for tokens not read from input
+    pub line: usize,
 }
 
 impl Token {
@@ -41,20 +48,27 @@ impl Token {
         Self {
             token_type,
             literal: literal.to_string(),
+            line: 0,
         }
     }
 }
 
+/// The reserved words and the token each one is read as.
+pub const KEYWORDS: &[(&str, TokenType)] = &[
+    ("let", LET),
+    ("fn", FUNCTION),
+    ("return", RETURN),
+    ("if", IF),
+    ("else", ELSE),
+    ("while", WHILE),
+    ("true", TRUE),
+    ("false", FALSE),
+    ("null", NULL),
+];
+
 pub fn get_identifier(ident: &str) -> TokenType {
-    let keyword_map: HashMap<&'static str, TokenType> = HashMap::from([
-        ("let", LET),
-        ("fn", FUNCTION),
-        ("return", RETURN),
-        ("if", IF),
-        ("else", ELSE),
-        ("true", TRUE),
-        ("false", FALSE),
-    ]);
+    let keyword_map: HashMap<&'static str, TokenType> =
+        HashMap::from_iter(KEYWORDS.iter().copied());
 
     if keyword_map.contains_key(ident) {
         return keyword_map.get(ident).unwrap();
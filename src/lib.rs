@@ -0,0 +1,23 @@
+#![allow(unused)]
+
+pub mod app;
+pub mod evaluator;
+pub mod formatter;
+mod interpreter;
+pub mod lexer;
+pub mod optimize;
+pub mod parser;
+pub mod repl;
+mod utils;
+
+pub use evaluator::object::{Frame, RuntimeError};
+pub use evaluator::{
+    context::{Ctx, Limits, ResourceExhausted, TestCase},
+    debugger::Debugger,
+    io::{IoPolicy, OutputBuffer},
+    object::{Arity, HostObject},
+};
+pub use interpreter::{
+    host::{FromObject, HostFn, IntoObject},
+    Error, Interpreter, Value,
+};
@@ -1,12 +1,6 @@
-#![allow(unused)]
-
-mod app;
-mod evaluator;
-mod lexer;
-mod parser;
-mod repl;
-mod utils;
+use interpreter_rust::app;
 
 fn main() {
-    repl::Repl::new().start();
+    let args = std::env::args().skip(1).collect();
+    std::process::exit(app::cli::run(args));
 }
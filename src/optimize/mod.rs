@@ -0,0 +1,321 @@
+use crate::parser::ast::*;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OptLevel {
+    /// Evaluate the tree exactly as parsed.
+    #[default]
+    O0,
+    /// Constant folding, dead-branch elimination and removal of
+    /// statements that follow a `return`.
+    O1,
+}
+
+impl OptLevel {
+    pub fn from_flag(flag: &str) -> Option<OptLevel> {
+        match flag {
+            "-O0" => Some(OptLevel::O0),
+            "-O1" => Some(OptLevel::O1),
+            _ => None,
+        }
+    }
+}
+
+/// Rewrites a program into an equivalent but cheaper one.
+///
+/// Only rewrites that cannot change what the evaluator produces are done:
+/// operations that would fail or overflow at runtime (e.g. `1 / 0`) are
+/// left in place so they still fail when evaluated.
+pub fn optimize(node: Node, level: OptLevel) -> Node {
+    match level {
+        OptLevel::O0 => node,
+        OptLevel::O1 => Optimizer.fold_node(node),
+    }
+}
+
+struct Optimizer;
+
+impl Optimizer {
+    // Folds an `if` and reports which branch it takes when the condition
+    // is a constant.
+    fn fold_if_parts(&mut self, expr: IfExpression) -> (Option<bool>, IfExpression) {
+        let condition = self.fold_node(Rc::unwrap_or_clone(expr.condition));
+        let taken = match &condition {
+            Node::Boolean(b) => Some(b.0),
+            Node::Int(_) | Node::Str(_) => Some(true),
+            Node::Null => Some(false),
+            _ => None,
+        };
+
+        let expr = IfExpression {
+            condition: Rc::new(condition),
+            consequence: self.fold_statements(expr.consequence),
+            alternative: self.fold_statements(expr.alternative),
+            line: expr.line,
+        };
+        (taken, expr)
+    }
+}
+
+impl Fold for Optimizer {
+    fn fold_statements(&mut self, statements: Vec<Node>) -> Vec<Node> {
+        let mut folded = vec![];
+        for stmt in statements {
+            match stmt {
+                // a decided `if` in statement position is replaced by the
+                // statements of the branch that will run, which evaluate in
+                // the same environment
+                Node::IfExpression(expr) => match self.fold_if_parts(expr) {
+                    (Some(taken), expr) => {
+                        let branch = if taken {
+                            expr.consequence
+                        } else {
+                            expr.alternative
+                        };
+                        if branch.iter().all(|stmt| matches!(stmt, Node::Comment(_))) {
+                            folded.push(Node::Null);
+                        }
+                        folded.extend(branch);
+                    }
+                    (None, expr) => folded.push(Node::IfExpression(expr)),
+                },
+                stmt => folded.push(self.fold_node(stmt)),
+            }
+
+            // nothing after a `return` is ever evaluated
+            if let Some(idx) = folded
+                .iter()
+                .position(|stmt| matches!(stmt, Node::ReturnStatement(_)))
+            {
+                folded.truncate(idx + 1);
+                break;
+            }
+        }
+        folded
+    }
+
+    // In expression position a decided `if` can only be replaced when its
+    // branch is empty or a single expression.
+    fn fold_if_expression(&mut self, expr: IfExpression) -> Node {
+        let (taken, expr) = self.fold_if_parts(expr);
+        let Some(taken) = taken else {
+            return Node::IfExpression(expr);
+        };
+
+        let branch: Vec<&Node> = if taken {
+            &expr.consequence
+        } else {
+            &expr.alternative
+        }
+        .iter()
+        .filter(|stmt| !matches!(stmt, Node::Comment(_)))
+        .collect();
+
+        match branch.as_slice() {
+            [] => Node::Null,
+            [stmt] if !matches!(stmt, Node::LetStatement(_) | Node::ReturnStatement(_)) => {
+                (*stmt).clone()
+            }
+            _ => Node::IfExpression(expr),
+        }
+    }
+
+    fn fold_prefix_expression(&mut self, expr: PrefixExpression) -> Node {
+        let right = self.fold_node(Rc::unwrap_or_clone(expr.right));
+
+        let folded = match (expr.op.as_str(), &right) {
+            ("-", Node::Int(v)) => v.0.checked_neg().map(|v| Node::Int(Integer(v))),
+            ("!", Node::Boolean(v)) => Some(Node::Boolean(Boolean(!v.0))),
+            ("!", Node::Int(_)) => Some(Node::Boolean(Boolean(false))),
+            ("!", Node::Null) => Some(Node::Boolean(Boolean(true))),
+            _ => None,
+        };
+
+        folded.unwrap_or_else(|| {
+            Node::Prefix(PrefixExpression {
+                line: expr.line,
+                op: expr.op,
+                right: Rc::new(right),
+            })
+        })
+    }
+
+    fn fold_infix_expression(&mut self, expr: InfixExpression) -> Node {
+        let left = self.fold_node(Rc::unwrap_or_clone(expr.left));
+        let right = self.fold_node(Rc::unwrap_or_clone(expr.right));
+
+        let folded = match (&left, &right) {
+            (Node::Int(l), Node::Int(r)) => fold_integer_infix(&expr.op, l.0, r.0),
+            _ => None,
+        };
+
+        folded.unwrap_or_else(|| {
+            Node::Infix(InfixExpression {
+                line: expr.line,
+                op: expr.op,
+                left: Rc::new(left),
+                right: Rc::new(right),
+            })
+        })
+    }
+}
+
+fn fold_integer_infix(op: &str, left: i32, right: i32) -> Option<Node> {
+    let int = |v: Option<i32>| v.map(|v| Node::Int(Integer(v)));
+    let boolean = |v: bool| Some(Node::Boolean(Boolean(v)));
+
+    match op {
+        "+" => int(left.checked_add(right)),
+        "-" => int(left.checked_sub(right)),
+        "*" => int(left.checked_mul(right)),
+        "/" => int(left.checked_div(right)),
+        "<" => boolean(left < right),
+        ">" => boolean(left > right),
+        "==" => boolean(left == right),
+        "!=" => boolean(left != right),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        evaluator::{environment::Environment, eval, object::Object, resolver::Resolver},
+        utils::setup,
+    };
+
+    fn run(input: &str, level: OptLevel) -> Option<Object> {
+        let (_, mut prog) = setup(input);
+        let env = Environment::new();
+        let mut resolver = Resolver::new(&env);
+        resolver.resolve_program(&mut prog);
+        assert!(resolver.errors.is_empty(), "{:?}", resolver.errors);
+
+        eval(&optimize(prog, level), env)
+    }
+
+    fn describe(obj: Option<Object>) -> String {
+        match obj {
+            Some(Object::Integer(v)) => format!("Integer({})", v.value),
+            Some(Object::Boolean(v)) => format!("Boolean({})", v.value),
+            Some(Object::Function(_)) => String::from("Function"),
+            Some(Object::Null) => String::from("Null"),
+            Some(other) => format!("{:?}", other),
+            None => String::from("None"),
+        }
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let tests = vec![
+            ("2 * (5 + 10);", "30"),
+            ("-5 + 10 * 2;", "15"),
+            ("(5 + 10 * 2 + 15 / 3) * 2 + -10;", "50"),
+            ("1 < 2;", "true"),
+            ("!(1 == 2);", "true"),
+            ("!10;", "false"),
+            ("let a = 2 * 3;", "let a = 6;"),
+            ("a + 2 * 3;", "(a + 6)"),
+            ("f(1 + 1, 2 * 2);", "f(2, 4)"),
+            (
+                "fn (x) { return x * (2 + 2); };",
+                "fn (x) { return (x * 4); }",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let (_, prog) = setup(input);
+            assert_eq!(optimize(prog, OptLevel::O1).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_folding_keeps_runtime_failures() {
+        let tests = vec![
+            ("1 / 0;", "(1 / 0)"),
+            ("1 / (2 - 2);", "(1 / 0)"),
+            ("2147483647 + 1;", "(2147483647 + 1)"),
+            ("true + 1;", "(true + 1)"),
+            ("-true;", "(-true)"),
+            ("true == true;", "(true == true)"),
+        ];
+
+        for (input, expected) in tests {
+            let (_, prog) = setup(input);
+            assert_eq!(optimize(prog, OptLevel::O1).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_dead_branch_elimination() {
+        let tests = vec![
+            ("if (true) { 1 } else { 2 };", "1"),
+            ("if (false) { 1 } else { 2 };", "2"),
+            ("if (1 > 2) { 1 } else { let a = 2; a };", "let a = 2;\na"),
+            ("if (false) { 1 };", "null"),
+            ("if (null) { 1 } else { 2 };", "2"),
+            ("let a = if (10) { 1 } else { 2 };", "let a = 1;"),
+            (
+                "let a = if (true) { let b = 1; b };",
+                "let a = if (true) { let b = 1; b } ;",
+            ),
+            ("if (x) { 1 } else { 2 };", "if (x) { 1 } else { 2 }"),
+        ];
+
+        for (input, expected) in tests {
+            let (_, prog) = setup(input);
+            assert_eq!(optimize(prog, OptLevel::O1).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_unreachable_statements_after_return() {
+        let tests = vec![
+            ("return 1; 2; 3;", "return 1;"),
+            ("fn () { return 1; 2; };", "fn () { return 1; }"),
+            ("if (true) { return 1; } 2;", "return 1;"),
+            ("if (x) { return 1; 2; } 3;", "if (x) { return 1; } \n3"),
+        ];
+
+        for (input, expected) in tests {
+            let (_, prog) = setup(input);
+            assert_eq!(optimize(prog, OptLevel::O1).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_optimized_matches_unoptimized() {
+        let tests = vec![
+            "2 * (5 + 10)",
+            "(5 + 10 * 2 + 15 / 3) * 2 + -10",
+            "!!10",
+            "5 > 4 == 3 < 4",
+            "if (5 == 5) { 5 } else { 10 }",
+            "if (5 != 5) { 5 } else { 10 }",
+            "if (false) { 5 }",
+            "5; if (false) { 5 }",
+            "let a = 1; if (true) { let a = 2; } a",
+            "let a = if (1 < 2) { 10 } else { 20 }; a * 2",
+            "let a = if (true) { return 1; }; a",
+            "let a = if (true) { let b = 1; return b; }; a",
+            "return 1; 2",
+            "let f = fn(x) { if (true) { return x * 2; } return 0; }; f(21)",
+            "let f = fn(x) { if (x > 1) { return x; } 0 }; f(1) + f(2)",
+            "let fact = fn(n) { if (n < 2) { return 1; } return n * fact(n - 1); }; fact(5)",
+            "let f = fn() { 3 * 3 }; f",
+            "true + 1",
+            "-true",
+            "true == true",
+            "!null",
+            "if (null) { 1 }",
+            "let a = if (false) { 1 }; a",
+        ];
+
+        for input in tests {
+            let expected = describe(run(input, OptLevel::O0));
+            let actual = describe(run(input, OptLevel::O1));
+            assert_eq!(actual, expected, "input: {}", input);
+        }
+    }
+}
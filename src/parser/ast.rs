@@ -6,13 +6,19 @@ pub enum Node {
     LetStatement(LetStatement),
     ReturnStatement(ReturnStatement),
     IfExpression(IfExpression),
+    While(WhileExpression),
+    Test(TestDeclaration),
     Function(FunctionLiteral),
     CallExpression(CallExpression),
+    Member(MemberExpression),
     Ident(Identifier),
     Int(Integer),
     Boolean(Boolean),
+    Str(StringLiteral),
+    Null,
     Prefix(PrefixExpression),
     Infix(InfixExpression),
+    Comment(Comment),
     Nil,
 }
 
@@ -23,17 +29,68 @@ impl Node {
             _ => false,
         }
     }
+    /// Source line of the nodes that record one: statements other than
+    /// expressions, calls, operators, member accesses and identifiers.
+    pub fn line(&self) -> Option<usize> {
+        let line = match self {
+            Node::LetStatement(v) => v.line,
+            Node::ReturnStatement(v) => v.line,
+            Node::IfExpression(v) => v.line,
+            Node::While(v) => v.line,
+            Node::Test(v) => v.line,
+            Node::CallExpression(v) => v.line,
+            Node::Member(v) => v.line,
+            Node::Prefix(v) => v.line,
+            Node::Infix(v) => v.line,
+            Node::Ident(v) => v.line,
+            _ => 0,
+        };
+        (line > 0).then_some(line)
+    }
+
+    /// A one-line description of the node without its children, e.g.
+    /// `Infix +` or `Let x`.
+    pub fn label(&self) -> String {
+        match self {
+            Node::Program(_) => String::from("Program"),
+            Node::BlockStatement(_) => String::from("Block"),
+            Node::LetStatement(v) => format!("Let {}", v.name.name),
+            Node::ReturnStatement(_) => String::from("Return"),
+            Node::IfExpression(_) => String::from("If"),
+            Node::While(_) => String::from("While"),
+            Node::Test(v) => format!("Test {}", quote_string(&v.name)),
+            Node::Function(v) => {
+                let parameters = Self::to_string_statements(&v.parameters, ", ");
+                format!("Function({})", parameters)
+            }
+            Node::CallExpression(_) => String::from("Call"),
+            Node::Member(v) => format!("Member .{}", v.property),
+            Node::Prefix(v) => format!("Prefix {}", v.op),
+            Node::Infix(v) => format!("Infix {}", v.op),
+            Node::Ident(v) => format!("Ident {}", v.name),
+            Node::Int(v) => format!("Int {}", v.0),
+            Node::Boolean(v) => format!("Boolean {}", v.0),
+            Node::Str(v) => format!("Str {}", quote_string(&v.0)),
+            Node::Null => String::from("Null"),
+            Node::Comment(v) => format!("Comment {}", v.text),
+            Node::Nil => String::from("Nil"),
+        }
+    }
+
     pub fn to_string(&self) -> String {
         match self {
             Node::Program(v) => Self::to_string_statements(&v.statements, "\n"),
             Node::BlockStatement(v) => Self::to_string_statements(&v.statements, "\n"),
-            Node::LetStatement(v) => format!("let {} = {};", v.name.0, v.value.to_string()),
+            Node::LetStatement(v) => format!("let {} = {};", v.name.name, v.value.to_string()),
             Node::ReturnStatement(v) => format!("return {};", v.value.to_string()),
             Node::Int(v) => v.0.to_string(),
             Node::Boolean(v) => v.0.to_string(),
-            Node::Ident(v) => v.0.to_string(),
+            Node::Str(v) => quote_string(&v.0),
+            Node::Null => String::from("null"),
+            Node::Ident(v) => v.name.to_string(),
             Node::Prefix(v) => format!("({}{})", v.op, v.right.to_string()),
             Node::Infix(v) => format!("({} {} {})", v.left.to_string(), v.op, v.right.to_string()),
+            Node::Member(v) => format!("{}.{}", v.object.to_string(), v.property),
             Node::IfExpression(v) => {
                 let condition = v.condition.to_string();
                 let consequence = Self::to_string_statements(&v.consequence, " ");
@@ -46,6 +103,17 @@ impl Node {
                 };
                 format!("if ({condition}) {{ {consequence} }} {alternative}")
             }
+            Node::While(v) => {
+                let body = Self::to_string_statements(&v.body, " ");
+                format!("while ({}) {{ {} }}", v.condition.to_string(), body)
+            }
+            Node::Test(v) => {
+                let Node::BlockStatement(body) = v.body.as_ref() else {
+                    return String::from("");
+                };
+                let body = Self::to_string_statements(&body.statements, " ");
+                format!("test {} {{ {} }}", quote_string(&v.name), body)
+            }
             Node::Function(v) => {
                 let parameters = Self::to_string_statements(&v.parameters, ", ");
                 let Node::BlockStatement(body) = v.body.as_ref() else {
@@ -58,12 +126,10 @@ impl Node {
                 let args = Self::to_string_statements(&v.arguments, ", ");
                 format!("{}({})", v.function.to_string(), args)
             }
+            Node::Comment(v) => v.text.clone(),
             _ => String::from(""),
         }
     }
-    fn tab_width(w: usize, s: &str) -> String {
-        format!("{}{}", "\t".repeat(w), s)
-    }
     fn to_string_statements(stmts: &[Node], sep: &str) -> String {
         stmts
             .iter()
@@ -87,27 +153,56 @@ pub struct BlockStatement {
 pub struct FunctionLiteral {
     pub parameters: Vec<Node>,
     pub body: Rc<Node>,
+    /// The names of the slots of a call, in order, filled in by the
+    /// resolver.
+    pub locals: Rc<Vec<String>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct CallExpression {
     pub function: Rc<Node>,
     pub arguments: Vec<Node>,
+    pub line: usize,
 }
 
 #[derive(Debug, Clone)]
 pub struct LetStatement {
     pub name: Identifier,
     pub value: Rc<Node>,
+    pub line: usize,
 }
 
 #[derive(Debug, Clone)]
 pub struct ReturnStatement {
     pub value: Rc<Node>,
+    pub line: usize,
+}
+
+/// Location of a binding computed by the resolver: `depth` counts the
+/// function scopes to walk outwards and `index` is the slot in that scope.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Slot {
+    pub depth: usize,
+    pub index: usize,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct Identifier(pub String);
+pub struct Identifier {
+    pub name: String,
+    pub slot: Option<Slot>,
+    // 1-based source line, 0 for identifiers not read from source
+    pub line: usize,
+}
+
+impl Identifier {
+    pub fn new<T: Into<String>>(name: T) -> Self {
+        Identifier {
+            name: name.into(),
+            slot: None,
+            line: 0,
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Integer(pub i32);
@@ -115,10 +210,38 @@ pub struct Integer(pub i32);
 #[derive(Debug, Clone, PartialEq)]
 pub struct Boolean(pub bool);
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringLiteral(pub String);
+
+/// Writes `s` as a string literal that the lexer reads back as `s`.
+pub fn quote_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[derive(Debug, Clone)]
+pub struct MemberExpression {
+    pub object: Rc<Node>,
+    // property names are looked up on the object, never in scope
+    pub property: String,
+    pub line: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct PrefixExpression {
     pub op: String,
     pub right: Rc<Node>,
+    pub line: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -126,6 +249,16 @@ pub struct InfixExpression {
     pub op: String,
     pub right: Rc<Node>,
     pub left: Rc<Node>,
+    pub line: usize,
+}
+
+/// A `//` comment kept in a statement list so that it can be printed back
+/// by the formatter. `trailing` is set when it followed other code on the
+/// same line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub trailing: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -133,11 +266,380 @@ pub struct IfExpression {
     pub condition: Rc<Node>,
     pub consequence: Vec<Node>, // block statement
     pub alternative: Vec<Node>,
+    pub line: usize,
+}
+
+/// `while (condition) { body }`, which evaluates to `null`.
+#[derive(Debug, Clone)]
+pub struct WhileExpression {
+    pub condition: Rc<Node>,
+    pub body: Vec<Node>,
+    pub line: usize,
+}
+
+/// `test "name" { body }`, a test case run by the `test` command. The
+/// body is a scope of its own, like a function body without parameters.
+#[derive(Debug, Clone)]
+pub struct TestDeclaration {
+    pub name: String,
+    pub body: Rc<Node>,
+    pub line: usize,
+}
+
+/// Read-only traversal of the AST.
+///
+/// Every method defaults to walking the children of its node, so an
+/// implementation only overrides the nodes it is interested in and calls
+/// the matching `walk_*` function when it still wants to descend.
+/// Declarations (`let` names and function parameters) go through
+/// `visit_binding`, references through `visit_identifier`.
+pub trait Visitor: Sized {
+    fn visit_node(&mut self, node: &Node) {
+        walk_node(self, node)
+    }
+    fn visit_statements(&mut self, stmts: &[Node]) {
+        walk_statements(self, stmts)
+    }
+    fn visit_program(&mut self, v: &Program) {
+        walk_statements(self, &v.statements)
+    }
+    fn visit_block_statement(&mut self, v: &BlockStatement) {
+        walk_statements(self, &v.statements)
+    }
+    fn visit_let_statement(&mut self, v: &LetStatement) {
+        walk_let_statement(self, v)
+    }
+    fn visit_return_statement(&mut self, v: &ReturnStatement) {
+        walk_return_statement(self, v)
+    }
+    fn visit_if_expression(&mut self, v: &IfExpression) {
+        walk_if_expression(self, v)
+    }
+    fn visit_while_expression(&mut self, v: &WhileExpression) {
+        walk_while_expression(self, v)
+    }
+    fn visit_test_declaration(&mut self, v: &TestDeclaration) {
+        walk_test_declaration(self, v)
+    }
+    fn visit_function_literal(&mut self, v: &FunctionLiteral) {
+        walk_function_literal(self, v)
+    }
+    fn visit_call_expression(&mut self, v: &CallExpression) {
+        walk_call_expression(self, v)
+    }
+    fn visit_member_expression(&mut self, v: &MemberExpression) {
+        walk_member_expression(self, v)
+    }
+    fn visit_prefix_expression(&mut self, v: &PrefixExpression) {
+        walk_prefix_expression(self, v)
+    }
+    fn visit_infix_expression(&mut self, v: &InfixExpression) {
+        walk_infix_expression(self, v)
+    }
+    fn visit_binding(&mut self, v: &Identifier) {}
+    fn visit_identifier(&mut self, v: &Identifier) {}
+    fn visit_integer(&mut self, v: &Integer) {}
+    fn visit_boolean(&mut self, v: &Boolean) {}
+    fn visit_string(&mut self, v: &StringLiteral) {}
+    fn visit_null(&mut self) {}
+    fn visit_comment(&mut self, v: &Comment) {}
+    fn visit_nil(&mut self) {}
+}
+
+pub fn walk_node<V: Visitor>(visitor: &mut V, node: &Node) {
+    match node {
+        Node::Program(v) => visitor.visit_program(v),
+        Node::BlockStatement(v) => visitor.visit_block_statement(v),
+        Node::LetStatement(v) => visitor.visit_let_statement(v),
+        Node::ReturnStatement(v) => visitor.visit_return_statement(v),
+        Node::IfExpression(v) => visitor.visit_if_expression(v),
+        Node::While(v) => visitor.visit_while_expression(v),
+        Node::Test(v) => visitor.visit_test_declaration(v),
+        Node::Function(v) => visitor.visit_function_literal(v),
+        Node::CallExpression(v) => visitor.visit_call_expression(v),
+        Node::Member(v) => visitor.visit_member_expression(v),
+        Node::Ident(v) => visitor.visit_identifier(v),
+        Node::Int(v) => visitor.visit_integer(v),
+        Node::Boolean(v) => visitor.visit_boolean(v),
+        Node::Str(v) => visitor.visit_string(v),
+        Node::Null => visitor.visit_null(),
+        Node::Prefix(v) => visitor.visit_prefix_expression(v),
+        Node::Infix(v) => visitor.visit_infix_expression(v),
+        Node::Comment(v) => visitor.visit_comment(v),
+        Node::Nil => visitor.visit_nil(),
+    }
+}
+
+pub fn walk_statements<V: Visitor>(visitor: &mut V, stmts: &[Node]) {
+    for stmt in stmts {
+        visitor.visit_node(stmt);
+    }
+}
+
+pub fn walk_let_statement<V: Visitor>(visitor: &mut V, v: &LetStatement) {
+    visitor.visit_node(&v.value);
+    visitor.visit_binding(&v.name);
+}
+
+pub fn walk_return_statement<V: Visitor>(visitor: &mut V, v: &ReturnStatement) {
+    visitor.visit_node(&v.value);
+}
+
+pub fn walk_if_expression<V: Visitor>(visitor: &mut V, v: &IfExpression) {
+    visitor.visit_node(&v.condition);
+    visitor.visit_statements(&v.consequence);
+    visitor.visit_statements(&v.alternative);
+}
+
+pub fn walk_while_expression<V: Visitor>(visitor: &mut V, v: &WhileExpression) {
+    visitor.visit_node(&v.condition);
+    visitor.visit_statements(&v.body);
+}
+
+pub fn walk_test_declaration<V: Visitor>(visitor: &mut V, v: &TestDeclaration) {
+    visitor.visit_node(&v.body);
+}
+
+pub fn walk_function_literal<V: Visitor>(visitor: &mut V, v: &FunctionLiteral) {
+    for param in &v.parameters {
+        match param {
+            Node::Ident(ident) => visitor.visit_binding(ident),
+            param => visitor.visit_node(param),
+        }
+    }
+    visitor.visit_node(&v.body);
+}
+
+pub fn walk_call_expression<V: Visitor>(visitor: &mut V, v: &CallExpression) {
+    visitor.visit_node(&v.function);
+    for arg in &v.arguments {
+        visitor.visit_node(arg);
+    }
+}
+
+pub fn walk_member_expression<V: Visitor>(visitor: &mut V, v: &MemberExpression) {
+    visitor.visit_node(&v.object);
+}
+
+pub fn walk_prefix_expression<V: Visitor>(visitor: &mut V, v: &PrefixExpression) {
+    visitor.visit_node(&v.right);
+}
+
+pub fn walk_infix_expression<V: Visitor>(visitor: &mut V, v: &InfixExpression) {
+    visitor.visit_node(&v.left);
+    visitor.visit_node(&v.right);
+}
+
+/// Owning, rebuilding traversal of the AST.
+///
+/// Each method takes a node by value and returns its replacement, which
+/// may be a different kind of node (e.g. an `if` folded into one of its
+/// branches). The defaults rebuild the node from its folded children
+/// through the matching `noop_fold_*` function.
+pub trait Fold: Sized {
+    fn fold_node(&mut self, node: Node) -> Node {
+        noop_fold_node(self, node)
+    }
+    fn fold_statements(&mut self, stmts: Vec<Node>) -> Vec<Node> {
+        noop_fold_statements(self, stmts)
+    }
+    fn fold_program(&mut self, v: Program) -> Node {
+        Node::Program(Program {
+            statements: self.fold_statements(v.statements),
+        })
+    }
+    fn fold_block_statement(&mut self, v: BlockStatement) -> Node {
+        Node::BlockStatement(BlockStatement {
+            statements: self.fold_statements(v.statements),
+        })
+    }
+    fn fold_let_statement(&mut self, v: LetStatement) -> Node {
+        noop_fold_let_statement(self, v)
+    }
+    fn fold_return_statement(&mut self, v: ReturnStatement) -> Node {
+        noop_fold_return_statement(self, v)
+    }
+    fn fold_if_expression(&mut self, v: IfExpression) -> Node {
+        noop_fold_if_expression(self, v)
+    }
+    fn fold_while_expression(&mut self, v: WhileExpression) -> Node {
+        noop_fold_while_expression(self, v)
+    }
+    fn fold_test_declaration(&mut self, v: TestDeclaration) -> Node {
+        noop_fold_test_declaration(self, v)
+    }
+    fn fold_function_literal(&mut self, v: FunctionLiteral) -> Node {
+        noop_fold_function_literal(self, v)
+    }
+    fn fold_call_expression(&mut self, v: CallExpression) -> Node {
+        noop_fold_call_expression(self, v)
+    }
+    fn fold_member_expression(&mut self, v: MemberExpression) -> Node {
+        noop_fold_member_expression(self, v)
+    }
+    fn fold_prefix_expression(&mut self, v: PrefixExpression) -> Node {
+        noop_fold_prefix_expression(self, v)
+    }
+    fn fold_infix_expression(&mut self, v: InfixExpression) -> Node {
+        noop_fold_infix_expression(self, v)
+    }
+    fn fold_binding(&mut self, v: Identifier) -> Identifier {
+        v
+    }
+    fn fold_identifier(&mut self, v: Identifier) -> Node {
+        Node::Ident(v)
+    }
+    fn fold_integer(&mut self, v: Integer) -> Node {
+        Node::Int(v)
+    }
+    fn fold_boolean(&mut self, v: Boolean) -> Node {
+        Node::Boolean(v)
+    }
+    fn fold_string(&mut self, v: StringLiteral) -> Node {
+        Node::Str(v)
+    }
+    fn fold_null(&mut self) -> Node {
+        Node::Null
+    }
+    fn fold_comment(&mut self, v: Comment) -> Node {
+        Node::Comment(v)
+    }
+    fn fold_nil(&mut self) -> Node {
+        Node::Nil
+    }
+    fn fold_rc(&mut self, node: Rc<Node>) -> Rc<Node> {
+        Rc::new(self.fold_node(Rc::unwrap_or_clone(node)))
+    }
+}
+
+pub fn noop_fold_node<F: Fold>(folder: &mut F, node: Node) -> Node {
+    match node {
+        Node::Program(v) => folder.fold_program(v),
+        Node::BlockStatement(v) => folder.fold_block_statement(v),
+        Node::LetStatement(v) => folder.fold_let_statement(v),
+        Node::ReturnStatement(v) => folder.fold_return_statement(v),
+        Node::IfExpression(v) => folder.fold_if_expression(v),
+        Node::While(v) => folder.fold_while_expression(v),
+        Node::Test(v) => folder.fold_test_declaration(v),
+        Node::Function(v) => folder.fold_function_literal(v),
+        Node::CallExpression(v) => folder.fold_call_expression(v),
+        Node::Member(v) => folder.fold_member_expression(v),
+        Node::Ident(v) => folder.fold_identifier(v),
+        Node::Int(v) => folder.fold_integer(v),
+        Node::Boolean(v) => folder.fold_boolean(v),
+        Node::Str(v) => folder.fold_string(v),
+        Node::Null => folder.fold_null(),
+        Node::Prefix(v) => folder.fold_prefix_expression(v),
+        Node::Infix(v) => folder.fold_infix_expression(v),
+        Node::Comment(v) => folder.fold_comment(v),
+        Node::Nil => folder.fold_nil(),
+    }
+}
+
+pub fn noop_fold_statements<F: Fold>(folder: &mut F, stmts: Vec<Node>) -> Vec<Node> {
+    stmts
+        .into_iter()
+        .map(|stmt| folder.fold_node(stmt))
+        .collect()
+}
+
+pub fn noop_fold_let_statement<F: Fold>(folder: &mut F, v: LetStatement) -> Node {
+    let value = folder.fold_rc(v.value);
+    Node::LetStatement(LetStatement {
+        name: folder.fold_binding(v.name),
+        value,
+        line: v.line,
+    })
+}
+
+pub fn noop_fold_return_statement<F: Fold>(folder: &mut F, v: ReturnStatement) -> Node {
+    Node::ReturnStatement(ReturnStatement {
+        value: folder.fold_rc(v.value),
+        line: v.line,
+    })
+}
+
+pub fn noop_fold_if_expression<F: Fold>(folder: &mut F, v: IfExpression) -> Node {
+    Node::IfExpression(IfExpression {
+        condition: folder.fold_rc(v.condition),
+        consequence: folder.fold_statements(v.consequence),
+        alternative: folder.fold_statements(v.alternative),
+        line: v.line,
+    })
+}
+
+pub fn noop_fold_while_expression<F: Fold>(folder: &mut F, v: WhileExpression) -> Node {
+    Node::While(WhileExpression {
+        condition: folder.fold_rc(v.condition),
+        body: folder.fold_statements(v.body),
+        line: v.line,
+    })
+}
+
+pub fn noop_fold_test_declaration<F: Fold>(folder: &mut F, v: TestDeclaration) -> Node {
+    Node::Test(TestDeclaration {
+        body: folder.fold_rc(v.body),
+        ..v
+    })
+}
+
+pub fn noop_fold_function_literal<F: Fold>(folder: &mut F, v: FunctionLiteral) -> Node {
+    let parameters = v
+        .parameters
+        .into_iter()
+        .map(|param| match param {
+            Node::Ident(ident) => Node::Ident(folder.fold_binding(ident)),
+            param => folder.fold_node(param),
+        })
+        .collect();
+    Node::Function(FunctionLiteral {
+        parameters,
+        body: folder.fold_rc(v.body),
+        locals: v.locals,
+    })
+}
+
+pub fn noop_fold_call_expression<F: Fold>(folder: &mut F, v: CallExpression) -> Node {
+    Node::CallExpression(CallExpression {
+        line: v.line,
+        function: folder.fold_rc(v.function),
+        arguments: v
+            .arguments
+            .into_iter()
+            .map(|arg| folder.fold_node(arg))
+            .collect(),
+    })
+}
+
+pub fn noop_fold_member_expression<F: Fold>(folder: &mut F, v: MemberExpression) -> Node {
+    Node::Member(MemberExpression {
+        line: v.line,
+        object: folder.fold_rc(v.object),
+        property: v.property,
+    })
+}
+
+pub fn noop_fold_prefix_expression<F: Fold>(folder: &mut F, v: PrefixExpression) -> Node {
+    Node::Prefix(PrefixExpression {
+        line: v.line,
+        op: v.op,
+        right: folder.fold_rc(v.right),
+    })
+}
+
+pub fn noop_fold_infix_expression<F: Fold>(folder: &mut F, v: InfixExpression) -> Node {
+    Node::Infix(InfixExpression {
+        line: v.line,
+        op: v.op,
+        left: folder.fold_rc(v.left),
+        right: folder.fold_rc(v.right),
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{lexer::Lexer, parser::Parser};
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser, utils::setup};
     #[test]
     fn test_node_to_string() {
         let tests = vec![
@@ -169,4 +671,59 @@ mod tests {
             assert_eq!(actual, test.to_owned());
         }
     }
+
+    #[test]
+    fn test_visitor_walks_every_node() {
+        #[derive(Default)]
+        struct Names {
+            bindings: Vec<String>,
+            references: Vec<String>,
+            integers: i32,
+        }
+
+        impl Visitor for Names {
+            fn visit_binding(&mut self, v: &Identifier) {
+                self.bindings.push(v.name.clone());
+            }
+            fn visit_identifier(&mut self, v: &Identifier) {
+                self.references.push(v.name.clone());
+            }
+            fn visit_integer(&mut self, v: &Integer) {
+                self.integers += v.0;
+            }
+        }
+
+        let (_, prog) =
+            setup("let f = fn(x, y) { if (x > 1) { return -y; } else { g(x, 2) } }; f(3, 4);");
+        let mut names = Names::default();
+        names.visit_node(&prog);
+
+        assert_eq!(names.bindings, vec!["x", "y", "f"]);
+        assert_eq!(names.references, vec!["x", "y", "g", "x", "f"]);
+        assert_eq!(names.integers, 10);
+    }
+
+    #[test]
+    fn test_fold_rebuilds_tree() {
+        struct Rename;
+
+        impl Fold for Rename {
+            fn fold_binding(&mut self, v: Identifier) -> Identifier {
+                Identifier::new(v.name.to_uppercase())
+            }
+            fn fold_identifier(&mut self, v: Identifier) -> Node {
+                Node::Ident(self.fold_binding(v))
+            }
+            fn fold_boolean(&mut self, v: Boolean) -> Node {
+                Node::Int(Integer(v.0 as i32))
+            }
+        }
+
+        let (_, prog) = setup("let f = fn(a, b) { if (true) { a } else { b(false) } }; f(1, 2);");
+        let actual = Rename.fold_node(prog).to_string();
+        assert_eq!(
+            actual,
+            "let F = fn (A, B) { if (1) { A } else { B(0) } };\nF(1, 2)"
+        );
+    }
 }
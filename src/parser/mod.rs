@@ -5,8 +5,8 @@ use std::{collections::HashMap, rc::Rc};
 use crate::{lexer, lexer::token};
 use ast::*;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-enum PrecedenceType {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum PrecedenceType {
     LOWEST = 0,
     EQUALS,
     LESSGREATER,
@@ -21,6 +21,12 @@ pub struct Parser {
     pub errors: Vec<String>,
     cur_token: token::Token,
     peek_token: token::Token,
+    // comments read before peek_token, and comments read up to cur_token
+    // that have not been placed in a statement list yet
+    peek_comments: Vec<Comment>,
+    comments: Vec<Comment>,
+    // set when a construct was cut short by the end of the input
+    unexpected_eof: bool,
 }
 
 impl Parser {
@@ -28,14 +34,11 @@ impl Parser {
         let mut parser = Parser {
             l,
             errors: vec![],
-            cur_token: token::Token {
-                token_type: token::EOF,
-                literal: "\0".to_string(),
-            },
-            peek_token: token::Token {
-                token_type: token::EOF,
-                literal: "\0".to_string(),
-            },
+            cur_token: token::Token::new(token::EOF, "\0"),
+            peek_token: token::Token::new(token::EOF, "\0"),
+            peek_comments: vec![],
+            comments: vec![],
+            unexpected_eof: false,
         };
 
         // advance tokens so that curToken contains the first token from the lexer
@@ -46,10 +49,25 @@ impl Parser {
     }
 
     // This function gets the next token from the lexer and updates cur_token
-    // and peek_token.
+    // and peek_token. Comments never reach the parsing functions, they are
+    // set aside and placed in the enclosing statement list by push_comments.
     fn next_token(&mut self) {
         self.cur_token = self.peek_token.clone();
+        self.comments.append(&mut self.peek_comments);
         self.peek_token = self.l.next_token();
+
+        while self.peek_token_is(token::COMMENT) {
+            self.peek_comments.push(Comment {
+                text: self.peek_token.literal.clone(),
+                trailing: self.peek_token.line == self.cur_token.line,
+            });
+            self.peek_token = self.l.next_token();
+        }
+    }
+
+    // Moves the comments read up to cur_token into a statement list.
+    fn push_comments(&mut self, stmts: &mut Vec<Node>) {
+        stmts.extend(self.comments.drain(..).map(Node::Comment));
     }
 
     // This function consumes lexer and produces a Node::Program representing
@@ -58,12 +76,14 @@ impl Parser {
         let mut program = Program { statements: vec![] };
 
         while !self.cur_token_is(token::EOF) {
+            self.push_comments(&mut program.statements);
             let stmt = self.parse_statement();
             if !stmt.is_nil() {
                 program.statements.push(stmt);
             }
             self.next_token();
         }
+        self.push_comments(&mut program.statements);
 
         return Node::Program(program);
     }
@@ -73,9 +93,17 @@ impl Parser {
     // The parser parses three types of statement variants
     // 1. LetStatements         - represents a let statement
     // 2. ReturnStatements      - represnts a return statement
-    // 3. Expression Statements - represents an any valid expression
+    // 3. TestDeclarations      - represents a test case
+    // 4. Expression Statements - represents an any valid expression
     fn parse_statement(&mut self) -> Node {
         match self.cur_token.token_type {
+            // `test` is not reserved, it only declares a test in front of
+            // a string, where a name could not be
+            token::IDENTIFIER
+                if self.cur_token.literal == "test" && self.peek_token_is(token::STRING) =>
+            {
+                self.parse_test_declaration().map_or(Node::Nil, Node::Test)
+            }
             token::LET => self
                 .parse_let_statement()
                 .map_or(Node::Nil, |v| Node::LetStatement(v)),
@@ -112,6 +140,14 @@ impl Parser {
             token::TRUE | token::FALSE => {
                 self.parse_boolean().map_or(Node::Nil, |v| Node::Boolean(v))
             }
+            token::NULL => Node::Null,
+            token::STRING => Node::Str(StringLiteral(self.cur_token.literal.clone())),
+            token::ILLEGAL if self.cur_token.literal.starts_with('"') => {
+                // the string runs to the end of the input
+                self.unexpected_eof = true;
+                self.new_error("unterminated string literal");
+                Node::Nil
+            }
             token::IDENTIFIER => self
                 .parse_identifier()
                 .map_or(Node::Nil, |v| Node::Ident(v)),
@@ -124,6 +160,7 @@ impl Parser {
             token::IF => self
                 .parse_if_expression()
                 .map_or(Node::Nil, |v| Node::IfExpression(v)),
+            token::WHILE => self.parse_while_expression().map_or(Node::Nil, Node::While),
             token::LPAREN => {
                 self.next_token();
                 let group = self.parse_expression(PrecedenceType::LOWEST);
@@ -138,6 +175,7 @@ impl Parser {
     }
 
     fn parse_if_expression(&mut self) -> Result<IfExpression, String> {
+        let line = self.cur_token.line;
         if !self.expect_peek(token::LPAREN) {
             return Err(String::from("Missing ("));
         }
@@ -157,12 +195,14 @@ impl Parser {
 
         // body
         while !self.cur_token_is(token::RBRACE) && !self.cur_token_is(token::EOF) {
+            self.push_comments(&mut consequence);
             let stmt = self.parse_statement();
             if !stmt.is_nil() {
                 consequence.push(stmt);
             }
             self.next_token();
         }
+        self.push_comments(&mut consequence);
 
         let mut alternative: Vec<Node> = vec![];
 
@@ -174,18 +214,50 @@ impl Parser {
 
             // body
             while !self.cur_token_is(token::RBRACE) && !self.cur_token_is(token::EOF) {
+                self.push_comments(&mut alternative);
                 let stmt = self.parse_statement();
                 if !stmt.is_nil() {
                     alternative.push(stmt);
                 }
                 self.next_token();
             }
+            self.push_comments(&mut alternative);
         }
 
         Ok(IfExpression {
             condition: Rc::new(condition),
             consequence,
             alternative,
+            line,
+        })
+    }
+
+    fn parse_while_expression(&mut self) -> Option<WhileExpression> {
+        let line = self.cur_token.line;
+        if !self.expect_peek(token::LPAREN) {
+            return None;
+        }
+        self.next_token();
+        let condition = self.parse_expression(PrecedenceType::LOWEST);
+        if !self.expect_peek(token::RPAREN) || !self.expect_peek(token::LBRACE) {
+            return None;
+        }
+
+        let mut body: Vec<Node> = vec![];
+        while !self.cur_token_is(token::RBRACE) && !self.cur_token_is(token::EOF) {
+            self.push_comments(&mut body);
+            let stmt = self.parse_statement();
+            if !stmt.is_nil() {
+                body.push(stmt);
+            }
+            self.next_token();
+        }
+        self.push_comments(&mut body);
+
+        Some(WhileExpression {
+            condition: Rc::new(condition),
+            body,
+            line,
         })
     }
 
@@ -204,13 +276,20 @@ impl Parser {
             token::LPAREN => self
                 .parse_call_expression(&left)
                 .map_or(Node::Nil, |v| Node::CallExpression(v)),
+            token::DOT => self
+                .parse_member_expression(left)
+                .map_or(Node::Nil, Node::Member),
             _ => Node::Nil,
         }
     }
 
     fn parse_let_statement(&mut self) -> Option<LetStatement> {
+        let line = self.cur_token.line;
         self.next_token(); // advance token to identifier
-        let name = Identifier(self.cur_token.literal.clone());
+        let name = Identifier {
+            line: self.cur_token.line,
+            ..Identifier::new(self.cur_token.literal.clone())
+        };
 
         if !self.expect_peek(token::ASSIGN) {
             return None;
@@ -227,18 +306,50 @@ impl Parser {
         Some(LetStatement {
             name,
             value: Rc::new(value),
+            line,
+        })
+    }
+
+    fn parse_test_declaration(&mut self) -> Option<TestDeclaration> {
+        let line = self.cur_token.line;
+        if !self.expect_peek(token::STRING) {
+            return None;
+        }
+        let name = self.cur_token.literal.clone();
+        if !self.expect_peek(token::LBRACE) {
+            return None;
+        }
+
+        let mut stmts: Vec<Node> = vec![];
+        while !self.cur_token_is(token::RBRACE) && !self.cur_token_is(token::EOF) {
+            self.push_comments(&mut stmts);
+            let stmt = self.parse_statement();
+            if !stmt.is_nil() {
+                stmts.push(stmt);
+            }
+            self.next_token();
+        }
+        self.push_comments(&mut stmts);
+
+        Some(TestDeclaration {
+            name,
+            body: Rc::new(Node::BlockStatement(BlockStatement { statements: stmts })),
+            line,
         })
     }
 
     fn parse_return_statement(&mut self) -> Option<ReturnStatement> {
+        let line = self.cur_token.line;
         self.next_token();
         let return_value = self.parse_expression(PrecedenceType::LOWEST);
         Some(ReturnStatement {
             value: Rc::new(return_value),
+            line,
         })
     }
 
     fn parse_call_expression(&mut self, left: &Node) -> Result<CallExpression, String> {
+        let line = self.cur_token.line;
         self.next_token(); // cur_token is '('
 
         let mut arguments: Vec<Node> = vec![];
@@ -263,11 +374,27 @@ impl Parser {
         }
 
         Ok(CallExpression {
+            line,
             arguments,
             function: Rc::new(left.clone()),
         })
     }
 
+    // `object.property`, where the property is a plain name rather than an
+    // expression. A call on a member expression is a method call.
+    fn parse_member_expression(&mut self, left: &Node) -> Option<MemberExpression> {
+        let line = self.cur_token.line;
+        if !self.expect_peek(token::IDENTIFIER) {
+            return None;
+        }
+
+        Some(MemberExpression {
+            line,
+            object: Rc::new(left.clone()),
+            property: self.cur_token.literal.clone(),
+        })
+    }
+
     fn parse_function_literal(&mut self) -> Option<FunctionLiteral> {
         if !self.expect_peek(token::LPAREN) {
             return None;
@@ -301,16 +428,19 @@ impl Parser {
 
         // body
         while !self.cur_token_is(token::RBRACE) && !self.cur_token_is(token::EOF) {
+            self.push_comments(&mut stmts);
             let stmt = self.parse_statement();
             if !stmt.is_nil() {
                 stmts.push(stmt);
             }
             self.next_token();
         }
+        self.push_comments(&mut stmts);
 
         Some(FunctionLiteral {
             parameters: parameters,
             body: Rc::new(Node::BlockStatement(BlockStatement { statements: stmts })),
+            locals: Rc::default(),
         })
     }
 
@@ -329,7 +459,10 @@ impl Parser {
     }
 
     fn parse_identifier(&self) -> Result<Identifier, String> {
-        Ok(Identifier(self.cur_token.literal.to_owned()))
+        Ok(Identifier {
+            line: self.cur_token.line,
+            ..Identifier::new(self.cur_token.literal.to_owned())
+        })
     }
 
     fn parse_prefix_expression(&mut self) -> Result<PrefixExpression, String> {
@@ -338,6 +471,7 @@ impl Parser {
         let right = self.parse_expression(PrecedenceType::PREFIX);
 
         Ok(PrefixExpression {
+            line: cur_tk.line,
             op: cur_tk.literal.clone(),
             right: Rc::new(right),
         })
@@ -352,6 +486,7 @@ impl Parser {
         let right = self.parse_expression(precedence);
 
         Ok(InfixExpression {
+            line: cur_tk.line,
             left: Rc::new(left.clone()),
             op: cur_tk.literal.clone(),
             right: Rc::new(right),
@@ -364,13 +499,20 @@ impl Parser {
             return true;
         }
         // if self.cur_token is not expected tk, push error
+        self.unexpected_eof |= self.peek_token_is(token::EOF);
         self.errors.push(format!(
-            "Expected peek_token to be {}, got {}",
-            tk, self.peek_token.token_type
+            "line {}: Expected peek_token to be {}, got {}",
+            self.peek_token.line, tk, self.peek_token.token_type
         ));
         return false;
     }
 
+    /// Whether parsing failed because the input ended in the middle of a
+    /// statement, so that more input could still make it valid.
+    pub fn hit_eof(&self) -> bool {
+        self.unexpected_eof
+    }
+
     fn cur_token_is(&self, tk: token::TokenType) -> bool {
         self.cur_token.token_type == tk
     }
@@ -388,18 +530,25 @@ impl Parser {
     }
 
     fn get_token_precedence(&self, tt: token::TokenType) -> PrecedenceType {
-        match tt {
-            token::LPAREN => PrecedenceType::CALL,
-            token::ASTERISK | token::SLASH => PrecedenceType::PRODUCT,
-            token::PLUS | token::MINUS => PrecedenceType::ADD,
-            token::LT | token::GT => PrecedenceType::LESSGREATER,
-            token::EQ | token::NOTEQ => PrecedenceType::EQUALS,
-            _ => PrecedenceType::LOWEST,
-        }
+        precedence_of(tt)
     }
 
     fn new_error(&mut self, message: &str) {
-        self.errors.push(message.to_string());
+        self.errors
+            .push(format!("line {}: {}", self.cur_token.line, message));
+    }
+}
+
+// Binding power of an infix operator. Operators are spelled the same as
+// their token types, so this also accepts `InfixExpression::op`.
+pub(crate) fn precedence_of(op: &str) -> PrecedenceType {
+    match op {
+        token::LPAREN | token::DOT => PrecedenceType::CALL,
+        token::ASTERISK | token::SLASH => PrecedenceType::PRODUCT,
+        token::PLUS | token::MINUS => PrecedenceType::ADD,
+        token::LT | token::GT => PrecedenceType::LESSGREATER,
+        token::EQ | token::NOTEQ => PrecedenceType::EQUALS,
+        _ => PrecedenceType::LOWEST,
     }
 }
 
@@ -465,6 +614,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parsing_null() {
+        let input = "
+            null;
+            let a = null;
+        ";
+        let input_size = utils::count_statements(input);
+        let (parser, prog) = setup(&input);
+
+        let Node::Program(prog) = prog else {
+            assert!(false, "prog is not Node::Program");
+            return;
+        };
+
+        utils::check_parser_errors(&parser);
+        utils::assert_program_len(&prog, input_size);
+
+        assert!(matches!(prog.statements[0], Node::Null));
+        let Node::LetStatement(stmt) = &prog.statements[1] else {
+            panic!("expected Node::LetStatement");
+        };
+        assert!(matches!(stmt.value.as_ref(), Node::Null));
+    }
+
+    #[test]
+    fn test_parsing_strings() {
+        let (parser, prog) = setup("\"hello\" + \"a\\tb\";");
+        utils::check_parser_errors(&parser);
+        assert_eq!(prog.to_string(), "(\"hello\" + \"a\\tb\")");
+
+        let (parser, _) = setup("let s = \"open;");
+        assert_eq!(parser.errors[0], "line 1: unterminated string literal");
+    }
+
     #[test]
     fn test_parsing_identifiers() {
         let input = "
@@ -488,7 +671,7 @@ mod tests {
             let actual = prog.statements.get(idx).unwrap();
             match actual {
                 Node::Ident(actual) => {
-                    assert_eq!(&actual.0, test);
+                    assert_eq!(&actual.name, test);
                 }
                 _ => {
                     assert!(false, "expected Node::Ident, got=.");
@@ -641,7 +824,7 @@ mod tests {
             let actual = prog.statements.get(idx).unwrap();
             match actual {
                 Node::LetStatement(actual) => {
-                    assert_eq!(&actual.name.0, test.0);
+                    assert_eq!(&actual.name.name, test.0);
                     utils::assert_integer_type(actual.value.as_ref(), test.1);
                 }
                 _ => {
@@ -732,14 +915,20 @@ mod tests {
             utils::check_parser_errors(&parser);
 
             let Node::Function(f) = prog.statements.get(0).unwrap() else {
-                assert!(prog.statements.get(0).is_none(), "Expected prog.statements.len()=1, got=0");
+                assert!(
+                    prog.statements.get(0).is_none(),
+                    "Expected prog.statements.len()=1, got=0"
+                );
                 return;
             };
 
             assert_eq!(test.params.len(), f.parameters.len());
 
             let Node::BlockStatement(body) = f.body.as_ref() else {
-                assert!(prog.statements.get(0).is_none(), "Expected prog.statements[0] to be Node::BlockStatement, got=.");
+                assert!(
+                    prog.statements.get(0).is_none(),
+                    "Expected prog.statements[0] to be Node::BlockStatement, got=."
+                );
                 return;
             };
 
@@ -773,6 +962,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parsing_while() {
+        let (parser, prog) = setup("while (a < b) { f(a); }");
+        utils::check_parser_errors(&parser);
+        assert_eq!(prog.to_string(), "while ((a < b)) { f(a) }");
+    }
+
+    #[test]
+    fn test_parsing_test_declarations() {
+        let tests = vec![
+            (
+                "test \"adds\" { let a = 1; a + 1 }",
+                "test \"adds\" { let a = 1; (a + 1) }",
+            ),
+            // `test` is still a name everywhere else
+            ("let test = 1; test + 1", "let test = 1;\n(test + 1)"),
+            ("test(\"a\")", "test(\"a\")"),
+        ];
+
+        for (input, expected) in tests {
+            let (parser, prog) = setup(input);
+            utils::check_parser_errors(&parser);
+            assert_eq!(prog.to_string(), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_parsing_member_expressions() {
+        let tests = vec![
+            ("p.x", "p.x"),
+            ("p.x + q.y * 2", "(p.x + (q.y * 2))"),
+            ("-p.x", "(-p.x)"),
+            ("p.scale(2).x", "p.scale(2).x"),
+            ("f(a).b.c(d)", "f(a).b.c(d)"),
+        ];
+
+        for (input, expected) in tests {
+            let (parser, prog) = setup(input);
+            utils::check_parser_errors(&parser);
+            assert_eq!(prog.to_string(), expected, "input: {}", input);
+        }
+
+        let (parser, _) = setup("p.1");
+        assert!(!parser.errors.is_empty());
+    }
+
     #[test]
     fn test_precedence_correctness() {
         assert!(true);
@@ -0,0 +1,557 @@
+use super::history::History;
+use std::{
+    io::{self, IsTerminal, Read, Write},
+    process::{Command, Stdio},
+};
+
+/// What the editor asks about the line being edited.
+pub trait Helper {
+    /// Candidates for completing `word`, the word before the cursor.
+    fn complete(&self, word: &str) -> Vec<String>;
+
+    /// `line` as it is shown, e.g. with colors added. Only escape
+    /// sequences that take no room on the screen may be added.
+    fn highlight(&self, line: &str) -> String {
+        line.to_string()
+    }
+}
+
+/// What reading a line ended with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReadLine {
+    Line(String),
+    /// Ctrl-C: the line typed so far was discarded.
+    Interrupted,
+    /// Ctrl-D on an empty line, or the end of the input.
+    Eof,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Key {
+    Char(char),
+    Ctrl(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Escape,
+    Unknown,
+}
+
+/// A line editor for the REPL, with cursor movement, history and
+/// reverse search. It puts the terminal in raw mode with `stty` while a
+/// line is read, and falls back to reading plain lines when stdin is not
+/// a terminal.
+pub struct Editor {
+    history: History,
+}
+
+impl Editor {
+    pub fn new(history: History) -> Self {
+        Editor { history }
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn add_history(&mut self, line: &str) {
+        self.history.add(line);
+    }
+
+    /// Reads a line after printing `prompt`, using `helper` to complete
+    /// words on Tab and to highlight the line.
+    pub fn read_line(&mut self, prompt: &str, helper: &dyn Helper) -> io::Result<ReadLine> {
+        let mut stdout = io::stdout();
+        let raw = if io::stdin().is_terminal() {
+            RawMode::enable()
+        } else {
+            None
+        };
+        let Some(_raw) = raw else {
+            write!(stdout, "{}", prompt)?;
+            stdout.flush()?;
+            let mut line = String::new();
+            if io::stdin().read_line(&mut line)? == 0 {
+                return Ok(ReadLine::Eof);
+            }
+            let line = line.trim_end_matches(['\n', '\r']);
+            return Ok(ReadLine::Line(line.to_string()));
+        };
+
+        self.edit(prompt, helper, &mut io::stdin().lock(), &mut stdout)
+    }
+
+    // Reads keys from `input` until the line is done, echoing the line
+    // to `output` as it changes.
+    fn edit<R: Read, W: Write>(
+        &mut self,
+        prompt: &str,
+        helper: &dyn Helper,
+        input: &mut R,
+        output: &mut W,
+    ) -> io::Result<ReadLine> {
+        let mut line = LineBuffer::default();
+        // the history entry shown, and the line that was being typed
+        // before moving into the history
+        let mut shown = self.history.entries().len();
+        let mut typed = String::new();
+
+        refresh(output, prompt, &line, helper)?;
+        loop {
+            let Some(key) = read_key(input)? else {
+                return finish(output, ReadLine::Eof);
+            };
+            match key {
+                Key::Enter => return finish(output, ReadLine::Line(line.text())),
+                Key::Ctrl('c') => {
+                    write!(output, "^C")?;
+                    return finish(output, ReadLine::Interrupted);
+                }
+                Key::Ctrl('d') if line.chars.is_empty() => return finish(output, ReadLine::Eof),
+                Key::Ctrl('d') | Key::Delete => line.delete(),
+                Key::Char(c) => line.insert(c),
+                Key::Backspace | Key::Ctrl('h') => line.backspace(),
+                Key::Left | Key::Ctrl('b') => line.cursor = line.cursor.saturating_sub(1),
+                Key::Right | Key::Ctrl('f') => {
+                    line.cursor = (line.cursor + 1).min(line.chars.len())
+                }
+                Key::Home | Key::Ctrl('a') => line.cursor = 0,
+                Key::End | Key::Ctrl('e') => line.cursor = line.chars.len(),
+                Key::Ctrl('k') => line.chars.truncate(line.cursor),
+                Key::Ctrl('u') => {
+                    line.chars.drain(..line.cursor);
+                    line.cursor = 0;
+                }
+                Key::Ctrl('l') => write!(output, "\x1B[2J\x1B[0;0H")?,
+                Key::Up | Key::Ctrl('p') if shown > 0 => {
+                    if shown == self.history.entries().len() {
+                        typed = line.text();
+                    }
+                    shown -= 1;
+                    line.set(&self.history.entries()[shown]);
+                }
+                Key::Down | Key::Ctrl('n') if shown < self.history.entries().len() => {
+                    shown += 1;
+                    match self.history.entries().get(shown) {
+                        Some(entry) => line.set(entry),
+                        None => line.set(&typed),
+                    }
+                }
+                Key::Ctrl('r') => {
+                    if let Some(done) = self.search(input, output, &mut line)? {
+                        return finish(output, done);
+                    }
+                }
+                Key::Tab => {
+                    let candidates = line.complete(helper);
+                    if candidates.len() > 1 {
+                        write!(output, "\r\n{}\r\n", candidates.join("  "))?;
+                    }
+                }
+                _ => {}
+            }
+            refresh(output, prompt, &line, helper)?;
+        }
+    }
+
+    // Ctrl-R: each key typed narrows the search to older entries
+    // containing the query, Ctrl-R again moves to the next older match.
+    // Enter runs the match, Ctrl-C or Ctrl-G leave `line` as it was and
+    // any other key keeps the match for editing.
+    fn search<R: Read, W: Write>(
+        &mut self,
+        input: &mut R,
+        output: &mut W,
+        line: &mut LineBuffer,
+    ) -> io::Result<Option<ReadLine>> {
+        let mut query = String::new();
+        let mut found: Option<usize> = None;
+        loop {
+            let matched = found.map_or("", |idx| self.history.entries()[idx].as_str());
+            write!(output, "\r(reverse-i-search)`{}': {}\x1B[K", query, matched)?;
+            output.flush()?;
+
+            let Some(key) = read_key(input)? else {
+                return Ok(Some(ReadLine::Eof));
+            };
+            let before = self.history.entries().len();
+            match key {
+                Key::Char(c) => {
+                    query.push(c);
+                    found = self
+                        .history
+                        .search(&query, found.map_or(before, |idx| idx + 1));
+                }
+                Key::Backspace => {
+                    query.pop();
+                    found = self.history.search(&query, before);
+                }
+                Key::Ctrl('r') => {
+                    found = self
+                        .history
+                        .search(&query, found.unwrap_or(before))
+                        .or(found);
+                }
+                Key::Ctrl('c') | Key::Ctrl('g') => return Ok(None),
+                Key::Enter => {
+                    let matched =
+                        found.map_or(String::new(), |idx| self.history.entries()[idx].clone());
+                    line.set(&matched);
+                    return Ok(Some(ReadLine::Line(matched)));
+                }
+                _ => {
+                    if let Some(idx) = found {
+                        line.set(&self.history.entries()[idx]);
+                    }
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct LineBuffer {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl LineBuffer {
+    fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    fn set(&mut self, text: &str) {
+        self.chars = text.chars().collect();
+        self.cursor = self.chars.len();
+    }
+
+    fn insert(&mut self, c: char) {
+        self.chars.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    // The word before the cursor: an identifier, or a meta-command when
+    // it starts the line with a `.`. Gives the index where it starts.
+    fn word(&self) -> Option<usize> {
+        let is_ident = |c: &char| c.is_alphanumeric() || *c == '_';
+        let len = self.chars[..self.cursor]
+            .iter()
+            .rev()
+            .take_while(|c| is_ident(c))
+            .count();
+        let start = self.cursor - len;
+        if self.chars.get(start).is_some_and(char::is_ascii_digit) {
+            return None;
+        }
+        if start == 0 {
+            return Some(0);
+        }
+        let before = self.chars[start - 1];
+        let line_start = self.chars[..start - 1].iter().all(|c| c.is_whitespace());
+        match before {
+            // a property, which can't be completed
+            '.' if !line_start => None,
+            '.' => Some(start - 1),
+            _ => Some(start),
+        }
+    }
+
+    // Completes the word before the cursor as far as all candidates
+    // agree, and returns the candidates when there is more than one.
+    fn complete(&mut self, helper: &dyn Helper) -> Vec<String> {
+        let Some(start) = self.word() else {
+            return vec![];
+        };
+        let word: String = self.chars[start..self.cursor].iter().collect();
+        let candidates = helper.complete(&word);
+        let Some(first) = candidates.first() else {
+            return vec![];
+        };
+
+        let mut common: Vec<char> = first.chars().collect();
+        for candidate in &candidates[1..] {
+            let len = common
+                .iter()
+                .zip(candidate.chars())
+                .take_while(|(a, b)| **a == *b)
+                .count();
+            common.truncate(len);
+        }
+        if candidates.len() == 1 {
+            common.push(' ');
+        }
+        let added = common.len().saturating_sub(self.cursor - start);
+        self.chars.splice(start..self.cursor, common);
+        self.cursor += added;
+
+        if candidates.len() > 1 {
+            candidates
+        } else {
+            vec![]
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.chars.len() {
+            self.chars.remove(self.cursor);
+        }
+    }
+}
+
+// Redraws the line and puts the cursor back in place.
+fn refresh<W: Write>(
+    output: &mut W,
+    prompt: &str,
+    line: &LineBuffer,
+    helper: &dyn Helper,
+) -> io::Result<()> {
+    write!(
+        output,
+        "\r{}{}\x1B[K",
+        prompt,
+        helper.highlight(&line.text())
+    )?;
+    let back = line.chars.len() - line.cursor;
+    if back > 0 {
+        write!(output, "\x1B[{}D", back)?;
+    }
+    output.flush()
+}
+
+fn finish<W: Write>(output: &mut W, result: ReadLine) -> io::Result<ReadLine> {
+    write!(output, "\r\n")?;
+    output.flush()?;
+    Ok(result)
+}
+
+fn read_byte<R: Read>(input: &mut R) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    match input.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+// Decodes one key press, or `None` at the end of the input. Escape
+// sequences cover what xterm and the Linux console send.
+fn read_key<R: Read>(input: &mut R) -> io::Result<Option<Key>> {
+    let Some(byte) = read_byte(input)? else {
+        return Ok(None);
+    };
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7F => Key::Backspace,
+        0x1B => read_escape(input)?,
+        1..=26 => Key::Ctrl(char::from(b'a' + byte - 1)),
+        0..=0x7F => Key::Char(char::from(byte)),
+        _ => read_utf8(input, byte)?,
+    };
+    Ok(Some(key))
+}
+
+fn read_escape<R: Read>(input: &mut R) -> io::Result<Key> {
+    let key = match read_byte(input)? {
+        Some(b'[') => {
+            let mut param = String::new();
+            loop {
+                match read_byte(input)? {
+                    Some(b) if b.is_ascii_digit() || b == b';' => param.push(char::from(b)),
+                    Some(b'A') => break Key::Up,
+                    Some(b'B') => break Key::Down,
+                    Some(b'C') => break Key::Right,
+                    Some(b'D') => break Key::Left,
+                    Some(b'H') => break Key::Home,
+                    Some(b'F') => break Key::End,
+                    Some(b'~') => match param.as_str() {
+                        "1" | "7" => break Key::Home,
+                        "3" => break Key::Delete,
+                        "4" | "8" => break Key::End,
+                        _ => break Key::Unknown,
+                    },
+                    _ => break Key::Unknown,
+                }
+            }
+        }
+        Some(b'O') => match read_byte(input)? {
+            Some(b'H') => Key::Home,
+            Some(b'F') => Key::End,
+            _ => Key::Unknown,
+        },
+        _ => Key::Escape,
+    };
+    Ok(key)
+}
+
+fn read_utf8<R: Read>(input: &mut R, first: u8) -> io::Result<Key> {
+    let len = match first {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => return Ok(Key::Unknown),
+    };
+    let mut bytes = vec![first];
+    for _ in 1..len {
+        match read_byte(input)? {
+            Some(b) => bytes.push(b),
+            None => return Ok(Key::Unknown),
+        }
+    }
+    let key = std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|s| s.chars().next())
+        .map_or(Key::Unknown, Key::Char);
+    Ok(key)
+}
+
+// Keeps the terminal in raw mode, where keys are read one at a time
+// without being echoed, until it is dropped.
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> Option<RawMode> {
+        let saved = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+        Some(RawMode {
+            saved: saved.trim().to_string(),
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&[self.saved.as_str()]);
+    }
+}
+
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Names;
+
+    impl Helper for Names {
+        fn complete(&self, word: &str) -> Vec<String> {
+            ["let", "len", "length", "puts", ".help"]
+                .iter()
+                .filter(|name| name.starts_with(word))
+                .map(|name| name.to_string())
+                .collect()
+        }
+    }
+
+    fn edit(editor: &mut Editor, keys: &str) -> ReadLine {
+        editor
+            .edit("> ", &Names, &mut keys.as_bytes(), &mut io::sink())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_editing_keys() {
+        let mut editor = Editor::new(History::new());
+        let tests = vec![
+            ("let a = 1;\r", ReadLine::Line(String::from("let a = 1;"))),
+            // left arrow twice, then insert
+            ("ac\x1B[D\x1B[Db\r", ReadLine::Line(String::from("bac"))),
+            ("ab\x02x\r", ReadLine::Line(String::from("axb"))),
+            ("abc\x7F\x7Fd\r", ReadLine::Line(String::from("ad"))),
+            ("abc\x01\x1B[3~\r", ReadLine::Line(String::from("bc"))),
+            ("abc\x01\x05d\r", ReadLine::Line(String::from("abcd"))),
+            ("abcd\x02\x02\x0B\r", ReadLine::Line(String::from("ab"))),
+            ("abcd\x02\x15\r", ReadLine::Line(String::from("d"))),
+            ("h\u{e9}llo\r", ReadLine::Line(String::from("h\u{e9}llo"))),
+            ("abc\x03", ReadLine::Interrupted),
+            ("\x04", ReadLine::Eof),
+            ("ab\x01\x04\r", ReadLine::Line(String::from("b"))),
+            ("abc", ReadLine::Eof),
+        ];
+
+        for (keys, expected) in tests {
+            assert_eq!(edit(&mut editor, keys), expected, "keys: {:?}", keys);
+        }
+    }
+
+    #[test]
+    fn test_tab_completion() {
+        let mut editor = Editor::new(History::new());
+        let tests = vec![
+            ("pu\t1)\r", "puts 1)"),
+            ("pu\t\x08(1)\r", "puts(1)"),
+            // ambiguous: completed as far as the candidates agree
+            ("l\t\r", "le"),
+            ("len\t\r", "len"),
+            ("lengt\t\r", "length "),
+            (".he\t\r", ".help "),
+            ("  .he\t\r", "  .help "),
+            ("p.le\t\r", "p.le"),
+            ("1le\t\r", "1le"),
+            ("x\t\r", "x"),
+            ("pu x\x01\x06\x06\t\r", "puts  x"),
+        ];
+        for (keys, expected) in tests {
+            assert_eq!(
+                edit(&mut editor, keys),
+                ReadLine::Line(String::from(expected)),
+                "keys: {:?}",
+                keys
+            );
+        }
+    }
+
+    #[test]
+    fn test_history_navigation_and_search() {
+        let mut editor = Editor::new(History::new());
+        for line in ["let a = 1;", "let b = 2;", "puts(a)"] {
+            editor.add_history(line);
+        }
+
+        let tests = vec![
+            ("\x1B[A\r", "puts(a)"),
+            ("\x1B[A\x1B[A\x1B[A\x1B[A\r", "let a = 1;"),
+            ("x\x1B[A\x1B[B\r", "x"),
+            ("\x10\x10\x0E\r", "puts(a)"),
+            ("\x12let\r", "let b = 2;"),
+            ("\x12let\x12\r", "let a = 1;"),
+            ("\x12b\x1B[C!\r", "let b = 2;!"),
+            ("x\x12puts\x07\r", "x"),
+        ];
+        for (keys, expected) in tests {
+            assert_eq!(
+                edit(&mut editor, keys),
+                ReadLine::Line(String::from(expected)),
+                "keys: {:?}",
+                keys
+            );
+        }
+    }
+}
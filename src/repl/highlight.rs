@@ -0,0 +1,193 @@
+use crate::{
+    evaluator::{environment::Environment, object::Object},
+    lexer::{token, Lexer},
+};
+use std::{
+    collections::HashSet,
+    env,
+    io::{self, IsTerminal},
+};
+
+const RED: &str = "31";
+const GREEN: &str = "32";
+const YELLOW: &str = "33";
+const MAGENTA: &str = "35";
+const CYAN: &str = "36";
+const GRAY: &str = "90";
+
+/// Whether output should be colored: stdout is a terminal and `NO_COLOR`
+/// is not set to anything.
+pub fn enabled() -> bool {
+    let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    !no_color && io::stdout().is_terminal()
+}
+
+/// `input` with its tokens colored by kind. Identifiers that are neither
+/// bound in `env` nor declared in `input` itself are shown in red.
+pub fn line(input: &str, env: &Environment) -> String {
+    let bound: HashSet<String> = env.names().into_iter().chain(declared(input)).collect();
+
+    // each token with where it ends, to look one token ahead
+    let mut tokens = vec![];
+    let mut lexer = Lexer::new(input);
+    loop {
+        let token = lexer.next_token();
+        if token.token_type == token::EOF {
+            break;
+        }
+        tokens.push((token, lexer.position.min(input.len())));
+    }
+
+    let mut out = String::new();
+    // the end of the last token, and how much of `input` is in `out`
+    let (mut end, mut written) = (0, 0);
+    let mut after_dot = false;
+    for (i, (token, token_end)) in tokens.iter().enumerate() {
+        // whatever the lexer skipped before the token is whitespace
+        let start = input.as_bytes()[end..]
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .map_or(input.len(), |i| end + i);
+        end = (*token_end).max(start);
+        // `test` is a keyword only in front of the name of a test
+        let declares_test = token.literal == "test"
+            && tokens
+                .get(i + 1)
+                .is_some_and(|(next, _)| next.token_type == token::STRING);
+
+        let color = match token.token_type {
+            token::LET
+            | token::FUNCTION
+            | token::RETURN
+            | token::IF
+            | token::ELSE
+            | token::WHILE => Some(MAGENTA),
+            token::IDENTIFIER if declares_test => Some(MAGENTA),
+            token::TRUE | token::FALSE | token::NULL | token::INT => Some(YELLOW),
+            token::STRING => Some(GREEN),
+            token::ILLEGAL if token.literal.starts_with('"') => Some(GREEN),
+            token::COMMENT => Some(GRAY),
+            token::IDENTIFIER if !after_dot && !bound.contains(&token.literal) => Some(RED),
+            token::IDENTIFIER | token::ILLEGAL => None,
+            _ => Some(CYAN),
+        };
+        // uncolored tokens are copied along with the text around them, as
+        // the lexer reads other characters than ASCII one byte at a time
+        if let Some(color) = color {
+            out.push_str(&input[written..start]);
+            out.push_str(&paint(Some(color), &input[start..end]));
+            written = end;
+        }
+        after_dot = token.token_type == token::DOT;
+    }
+    out.push_str(&input[written..]);
+    out
+}
+
+/// `value` as the REPL prints it, colored by its type.
+pub fn value(value: &Object) -> String {
+    paint(value_color(value), &value.to_string())
+}
+
+/// `text` colored as an error.
+pub fn error(text: &str) -> String {
+    paint(Some(RED), text)
+}
+
+fn value_color(value: &Object) -> Option<&'static str> {
+    match value {
+        Object::Integer(_) | Object::Boolean(_) => Some(YELLOW),
+        Object::String(_) => Some(GREEN),
+        Object::Function(_) | Object::Builtin(_) => Some(CYAN),
+        Object::Error(_) => Some(RED),
+        Object::Null => Some(GRAY),
+        Object::Return(v) => value_color(&v.value),
+        Object::ObjectRef(v) => v.try_borrow().ok().and_then(|v| value_color(&v)),
+        _ => None,
+    }
+}
+
+// The names `input` binds with `let` or as function parameters.
+fn declared(input: &str) -> Vec<String> {
+    let mut names = vec![];
+    let mut lexer = Lexer::new(input);
+    let mut previous = token::EOF;
+    let mut in_parameters = false;
+    loop {
+        let token = lexer.next_token();
+        match token.token_type {
+            token::EOF => break,
+            token::IDENTIFIER if previous == token::LET || in_parameters => {
+                names.push(token.literal)
+            }
+            token::LPAREN if previous == token::FUNCTION => in_parameters = true,
+            token::RPAREN => in_parameters = false,
+            _ => {}
+        }
+        previous = token.token_type;
+    }
+    names
+}
+
+fn paint(color: Option<&str>, text: &str) -> String {
+    match color {
+        Some(color) => format!("\x1B[{}m{}\x1B[0m", color, text),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Interpreter;
+
+    #[test]
+    fn test_line() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval("let known = 1;").unwrap();
+        let env = interpreter.env().borrow();
+
+        let tests = vec![
+            (
+                "let x = 12;",
+                "\x1B[35mlet\x1B[0m x \x1B[36m=\x1B[0m \x1B[33m12\x1B[0m\x1B[36m;\x1B[0m",
+            ),
+            (
+                "known + other",
+                "known \x1B[36m+\x1B[0m \x1B[31mother\x1B[0m",
+            ),
+            (
+                "fn(a) { a.b }",
+                "\x1B[35mfn\x1B[0m\x1B[36m(\x1B[0ma\x1B[36m)\x1B[0m \x1B[36m{\x1B[0m a\x1B[36m.\x1B[0mb \x1B[36m}\x1B[0m",
+            ),
+            (
+                "puts(\"hé\") // note",
+                "puts\x1B[36m(\x1B[0m\x1B[32m\"hé\"\x1B[0m\x1B[36m)\x1B[0m \x1B[90m// note\x1B[0m",
+            ),
+            ("\"open  ", "\x1B[32m\"open  \x1B[0m"),
+            ("true é", "\x1B[33mtrue\x1B[0m é"),
+            (
+                "test \"t\" {}",
+                "\x1B[35mtest\x1B[0m \x1B[32m\"t\"\x1B[0m \x1B[36m{\x1B[0m\x1B[36m}\x1B[0m",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(line(input, &env), expected, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_value() {
+        let mut interpreter = Interpreter::new();
+        let tests = vec![
+            ("1 + 2", "\x1B[33m3\x1B[0m"),
+            ("\"s\"", "\x1B[32ms\x1B[0m"),
+            ("null", "\x1B[90mnull\x1B[0m"),
+            ("puts", "\x1B[36m<builtin puts>\x1B[0m"),
+        ];
+        for (input, expected) in tests {
+            let v = interpreter.eval(input).unwrap();
+            assert_eq!(value(&v), expected, "input: {:?}", input);
+        }
+    }
+}
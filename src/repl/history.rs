@@ -0,0 +1,124 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+// Older entries are dropped once there are more than this.
+const MAX_ENTRIES: usize = 1000;
+
+/// Lines entered in the REPL, oldest first, kept in a file between
+/// sessions when one is given.
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History::default()
+    }
+
+    /// Loads the history kept in `path`, which does not have to exist
+    /// yet. `save` writes it back there.
+    pub fn with_file<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => contents.lines().map(String::from).collect(),
+            Err(_) => vec![],
+        };
+        let mut history = History {
+            entries,
+            path: Some(path),
+        };
+        history.truncate();
+        history
+    }
+
+    /// `$XDG_STATE_HOME/interpreter-rust/history`, or
+    /// `~/.interpreter-rust_history` when that is not set.
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(state) = env::var_os("XDG_STATE_HOME").filter(|dir| !dir.is_empty()) {
+            return Some(Path::new(&state).join("interpreter-rust").join("history"));
+        }
+        let home = env::var_os("HOME").filter(|dir| !dir.is_empty())?;
+        Some(Path::new(&home).join(".interpreter-rust_history"))
+    }
+
+    /// Adds `line` unless it is blank or repeats the last entry.
+    pub fn add(&mut self, line: &str) {
+        if line.trim().is_empty() || self.entries.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.entries.push(line.to_string());
+        self.truncate();
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Index of the newest entry before `before` containing `query`.
+    pub fn search(&self, query: &str, before: usize) -> Option<usize> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+
+    /// Writes the history to its file, creating the directory it is in.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut contents = self.entries.join("\n");
+        contents.push('\n');
+        fs::write(path, contents)
+    }
+
+    fn truncate(&mut self) {
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.drain(..self.entries.len() - MAX_ENTRIES);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch_dir;
+
+    #[test]
+    fn test_history_is_saved_and_loaded() {
+        let dir = scratch_dir("history");
+        let path = dir.join("nested").join("history");
+
+        let mut history = History::with_file(&path);
+        assert!(history.entries().is_empty());
+        for line in ["let a = 1;", "", "a", "a", "puts(a)"] {
+            history.add(line);
+        }
+        assert_eq!(history.entries(), ["let a = 1;", "a", "puts(a)"]);
+        history.save().unwrap();
+
+        let history = History::with_file(&path);
+        assert_eq!(history.entries(), ["let a = 1;", "a", "puts(a)"]);
+        assert_eq!(history.search("a", 3), Some(2));
+        assert_eq!(history.search("let", 2), Some(0));
+        assert_eq!(history.search("let", 0), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_history_keeps_newest_entries() {
+        let mut history = History::new();
+        for i in 0..MAX_ENTRIES + 5 {
+            history.add(&i.to_string());
+        }
+        assert_eq!(history.entries().len(), MAX_ENTRIES);
+        assert_eq!(history.entries()[0], "5");
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{
+    evaluator::environment::Environment,
+    lexer::{token, Lexer},
+    parser::ast::*,
+};
+
+/// One line per token of `input`: its line, type and literal.
+pub fn tokens(input: &str) -> String {
+    let mut lexer = Lexer::new(input);
+    let mut lines = vec![];
+    loop {
+        let token = lexer.next_token();
+        if token.token_type == token::EOF {
+            break;
+        }
+        lines.push(format!(
+            "{:>4}  {:<10} {}",
+            token.line, token.token_type, token.literal
+        ));
+    }
+    lines.join("\n")
+}
+
+/// The syntax tree below `node`, one node per line, with children
+/// indented under their parent.
+pub fn tree(node: &Node) -> String {
+    let mut lines = vec![];
+    push_tree(&mut lines, node, 0);
+    lines.join("\n")
+}
+
+fn push_tree(lines: &mut Vec<String>, node: &Node, depth: usize) {
+    let label = |lines: &mut Vec<String>, text: &str, depth: usize| {
+        lines.push(format!("{}{}", "  ".repeat(depth), text));
+    };
+    let own_label = |lines: &mut Vec<String>| label(lines, &node.label(), depth);
+    let children = |lines: &mut Vec<String>, nodes: &[Node], depth: usize| {
+        for node in nodes {
+            push_tree(lines, node, depth);
+        }
+    };
+
+    match node {
+        Node::Program(v) => {
+            own_label(lines);
+            children(lines, &v.statements, depth + 1);
+        }
+        Node::BlockStatement(v) => {
+            own_label(lines);
+            children(lines, &v.statements, depth + 1);
+        }
+        Node::LetStatement(v) => {
+            own_label(lines);
+            push_tree(lines, &v.value, depth + 1);
+        }
+        Node::ReturnStatement(v) => {
+            own_label(lines);
+            push_tree(lines, &v.value, depth + 1);
+        }
+        Node::IfExpression(v) => {
+            own_label(lines);
+            push_tree(lines, &v.condition, depth + 1);
+            label(lines, "Then", depth + 1);
+            children(lines, &v.consequence, depth + 2);
+            if !v.alternative.is_empty() {
+                label(lines, "Else", depth + 1);
+                children(lines, &v.alternative, depth + 2);
+            }
+        }
+        Node::While(v) => {
+            own_label(lines);
+            push_tree(lines, &v.condition, depth + 1);
+            label(lines, "Body", depth + 1);
+            children(lines, &v.body, depth + 2);
+        }
+        Node::Test(v) => {
+            own_label(lines);
+            push_tree(lines, &v.body, depth + 1);
+        }
+        Node::Function(v) => {
+            own_label(lines);
+            push_tree(lines, &v.body, depth + 1);
+        }
+        Node::CallExpression(v) => {
+            own_label(lines);
+            push_tree(lines, &v.function, depth + 1);
+            children(lines, &v.arguments, depth + 1);
+        }
+        Node::Member(v) => {
+            own_label(lines);
+            push_tree(lines, &v.object, depth + 1);
+        }
+        Node::Prefix(v) => {
+            own_label(lines);
+            push_tree(lines, &v.right, depth + 1);
+        }
+        Node::Infix(v) => {
+            own_label(lines);
+            push_tree(lines, &v.left, depth + 1);
+            push_tree(lines, &v.right, depth + 1);
+        }
+        Node::Ident(_)
+        | Node::Int(_)
+        | Node::Boolean(_)
+        | Node::Str(_)
+        | Node::Null
+        | Node::Comment(_)
+        | Node::Nil => own_label(lines),
+    }
+}
+
+/// Every name bound in `env` with its value, sorted by name.
+pub fn bindings(env: &Environment) -> String {
+    env.names()
+        .iter()
+        .filter_map(|name| {
+            let value = env.get(name)?;
+            let value = value.borrow().inspect();
+            Some(format!("{} = {}", name, value))
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{utils::setup, Interpreter};
+
+    #[test]
+    fn test_tokens() {
+        let expected = [
+            "   1  LET        let",
+            "   1  IDENTIFIER x",
+            "   2  STRING     a b",
+        ];
+        assert_eq!(tokens("let x\n\"a b\""), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_tree() {
+        let (_, prog) = setup("let f = fn(x) { if (x) { !x } else { f(x) } }; p.y + \"s\"");
+        let expected = [
+            "Program",
+            "  Let f",
+            "    Function(x)",
+            "      Block",
+            "        If",
+            "          Ident x",
+            "          Then",
+            "            Prefix !",
+            "              Ident x",
+            "          Else",
+            "            Call",
+            "              Ident f",
+            "              Ident x",
+            "  Infix +",
+            "    Member .y",
+            "      Ident p",
+            "    Str \"s\"",
+        ];
+        assert_eq!(tree(&prog), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_bindings() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval("let b = \"two\"; let a = 1;").unwrap();
+        let env = interpreter.env().borrow();
+        let listed = bindings(&env);
+        let lines: Vec<&str> = listed.lines().collect();
+        assert!(lines.contains(&"a = 1"), "{}", listed);
+        assert!(lines.contains(&"b = \"two\""), "{}", listed);
+        assert!(lines.contains(&"puts = <builtin puts>"), "{}", listed);
+    }
+}
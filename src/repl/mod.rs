@@ -1,89 +1,356 @@
+mod editor;
+mod highlight;
+mod history;
+mod inspect;
+mod session;
+
 use crate::{
-    evaluator::{environment::Environment, eval},
-    lexer::Lexer,
+    evaluator::environment::Environment,
+    lexer::{token, Lexer},
+    optimize::OptLevel,
     parser::Parser,
+    Error, Value,
 };
+use editor::{Editor, Helper, ReadLine};
+use history::History;
+use session::Session;
 use std::{
     io::{self, Write},
-    rc::Rc,
+    time::Instant,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum MetaCommand {
     Help,
     Clear,
     Ping,
+    Cancel,
+    Tokens,
+    Ast,
+    Env,
+    Type,
+    Time,
+    Load,
+    Save,
+    Reset,
     Exit,
 }
 
+// Every meta-command with its arguments and help text, in the order
+// `.help` lists them.
+const COMMANDS: &[(&str, MetaCommand, &str, &str)] = &[
+    (
+        ".ast",
+        MetaCommand::Ast,
+        "<code>",
+        "Print the syntax tree of <code>",
+    ),
+    (
+        ".cancel",
+        MetaCommand::Cancel,
+        "",
+        "Discard the incomplete input typed so far",
+    ),
+    (".clear", MetaCommand::Clear, "", "Clear the REPL"),
+    (
+        ".env",
+        MetaCommand::Env,
+        "",
+        "List the bindings of the session with their values",
+    ),
+    (".exit", MetaCommand::Exit, "", "Exit the REPL"),
+    (".help", MetaCommand::Help, "", "Print this help message"),
+    (
+        ".load",
+        MetaCommand::Load,
+        "<file>",
+        "Evaluate <file> in the session",
+    ),
+    (".ping", MetaCommand::Ping, "", "Print \"pong\""),
+    (
+        ".reset",
+        MetaCommand::Reset,
+        "",
+        "Discard all bindings and start a new session",
+    ),
+    (
+        ".save",
+        MetaCommand::Save,
+        "<file>",
+        "Write the inputs evaluated without errors to <file>",
+    ),
+    (
+        ".time",
+        MetaCommand::Time,
+        "<expr>",
+        "Evaluate <expr> and print how long it took and how many steps",
+    ),
+    (
+        ".tokens",
+        MetaCommand::Tokens,
+        "<code>",
+        "Print the tokens <code> is read as",
+    ),
+    (
+        ".type",
+        MetaCommand::Type,
+        "<expr>",
+        "Evaluate <expr> and print the type of its value",
+    ),
+];
+
 pub struct Repl {
     running: bool,
+    opt_level: OptLevel,
+    editor: Editor,
+    // highlight input and color results, see `highlight::enabled`
+    color: bool,
+}
+
+// Completes and highlights lines against the bindings of the session.
+struct SessionHelper<'a> {
+    session: &'a Session,
+    color: bool,
+}
+
+impl Helper for SessionHelper<'_> {
+    fn complete(&self, word: &str) -> Vec<String> {
+        Repl::completions(word, &self.session.interpreter.env().borrow())
+    }
+
+    fn highlight(&self, line: &str) -> String {
+        if !self.color || Repl::input_is_meta_command(line.trim_start()) {
+            return line.to_string();
+        }
+        highlight::line(line, &self.session.interpreter.env().borrow())
+    }
 }
 
 impl Repl {
+    /// Creates a REPL that keeps its history in `History::default_path`.
     pub fn new() -> Self {
-        Repl { running: true }
+        let history = History::default_path().map_or_else(History::new, History::with_file);
+        Repl {
+            running: true,
+            opt_level: OptLevel::default(),
+            editor: Editor::new(history),
+            color: highlight::enabled(),
+        }
+    }
+
+    pub fn with_opt_level(mut self, opt_level: OptLevel) -> Self {
+        self.opt_level = opt_level;
+        self
     }
     pub fn start(&mut self) -> io::Result<()> {
         Repl::print_information();
 
-        Repl::print_prompt();
-        let mut input = Repl::read_input()?;
-        let mut env = Environment::new();
+        let mut session = Session::new(self.opt_level);
+        // lines read so far of a program that is not complete yet
+        let mut pending = String::new();
 
         while self.running {
-            if Repl::input_is_meta_command(&input) {
-                let cmd = Repl::parse_command(&input);
-
-                if let Some(cmd) = cmd {
-                    match cmd {
-                        MetaCommand::Exit => {
-                            self.running = false;
-                            continue;
-                        }
-                        _ => Repl::do_meta_command(&cmd),
-                    }
+            let prompt = Repl::prompt(!pending.is_empty());
+            let helper = SessionHelper {
+                session: &session,
+                color: self.color,
+            };
+            let input = match self.editor.read_line(prompt, &helper)? {
+                ReadLine::Line(input) => input,
+                // Ctrl-C drops the incomplete program too
+                ReadLine::Interrupted => {
+                    pending.clear();
+                    continue;
                 }
-            } else {
-                let lexer = Lexer::new(&input);
-                let mut parser = Parser::new(lexer);
-                let prog = parser.parse_program();
+                ReadLine::Eof => break,
+            };
+            self.editor.add_history(&input);
 
-                if parser.errors.len() > 0 {
-                    for e in parser.errors {
-                        println!("{}", e);
+            if Repl::input_is_meta_command(input.trim()) {
+                let cmd = Repl::parse_command(input.trim());
+
+                match cmd {
+                    Some((MetaCommand::Exit, _)) => {
+                        self.running = false;
+                        continue;
+                    }
+                    Some((MetaCommand::Cancel, _)) => {
+                        pending.clear();
+                        continue;
                     }
-                } else {
-                    match eval(&prog, Rc::clone(&env)) {
-                        Some(v) => {
-                            println!("{:?}", v);
-                        }
-                        None => {
-                            println!();
-                        }
+                    Some((MetaCommand::Reset, _)) => {
+                        session = Session::new(self.opt_level);
+                        pending.clear();
+                        continue;
                     }
+                    Some((cmd, arg)) => {
+                        self.do_meta_command(&cmd, arg, &mut session);
+                        continue;
+                    }
+                    // may continue a member expression from the line before
+                    None if !pending.is_empty() => {}
+                    None => continue,
                 }
             }
 
-            Repl::print_prompt();
-            input = Repl::read_input()?;
+            if !pending.is_empty() {
+                pending.push('\n');
+            }
+            pending.push_str(&input);
+            if pending.trim().is_empty() {
+                pending.clear();
+                continue;
+            }
+            if Repl::is_incomplete(&pending) {
+                continue;
+            }
+
+            let result = session.eval(&pending);
+            self.print_result(result);
+            pending.clear();
         }
 
+        if let Err(e) = self.editor.history().save() {
+            eprintln!("could not save history: {}", e);
+        }
         Ok(())
     }
 
-    fn parse_command(input: &str) -> Option<MetaCommand> {
-        match input {
-            ".help" => Some(MetaCommand::Help),
-            ".clear" => Some(MetaCommand::Clear),
-            ".ping" => Some(MetaCommand::Ping),
-            ".exit" => Some(MetaCommand::Exit),
-            _ => None,
+    // Whether `input` stops in the middle of a statement: a bracket or a
+    // string is left open, or the parser ran into the end of the input.
+    fn is_incomplete(input: &str) -> bool {
+        let mut lexer = Lexer::new(input);
+        let mut depth = 0;
+        loop {
+            let token = lexer.next_token();
+            match token.token_type {
+                token::LPAREN | token::LBRACE => depth += 1,
+                token::RPAREN | token::RBRACE => depth -= 1,
+                token::ILLEGAL if token.literal.starts_with('"') => return true,
+                token::EOF => break,
+                _ => {}
+            }
+        }
+        if depth > 0 {
+            return true;
+        }
+
+        let mut parser = Parser::new(Lexer::new(input));
+        parser.parse_program();
+        parser.hit_eof()
+    }
+
+    // Splits `input` into the command and its argument.
+    fn parse_command(input: &str) -> Option<(MetaCommand, &str)> {
+        let (name, arg) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        COMMANDS
+            .iter()
+            .find(|(command, ..)| *command == name)
+            .map(|(_, cmd, ..)| (*cmd, arg.trim()))
+    }
+
+    // Candidates for the word being completed: meta-commands for a word
+    // starting with `.`, otherwise keywords and the names bound in `env`,
+    // which include the builtins.
+    fn completions(word: &str, env: &Environment) -> Vec<String> {
+        if word.starts_with('.') {
+            return COMMANDS
+                .iter()
+                .map(|(name, ..)| name.to_string())
+                .filter(|name| name.starts_with(word))
+                .collect();
         }
+        let mut candidates: Vec<String> = token::KEYWORDS
+            .iter()
+            .map(|(keyword, _)| keyword.to_string())
+            .chain(env.names())
+            .filter(|name| name.starts_with(word))
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        candidates
     }
 
-    fn do_meta_command(cmd: &MetaCommand) {
+    fn print_result(&self, result: Result<Value, Error>) {
+        match result {
+            Ok(v) if self.color => println!("{}", highlight::value(&v)),
+            Ok(v) => println!("{}", v),
+            Err(e) => self.print_error(&Repl::error_text(&e)),
+        }
+    }
+
+    // The error followed by the calls it came out of.
+    fn error_text(e: &Error) -> String {
+        let mut text = e.to_string();
+        for frame in e.backtrace() {
+            text.push_str(&format!("\n    at {}", frame));
+        }
+        text
+    }
+
+    fn print_error(&self, text: &str) {
+        if self.color {
+            println!("{}", highlight::error(text));
+        } else {
+            println!("{}", text);
+        }
+    }
+
+    fn do_meta_command(&self, cmd: &MetaCommand, arg: &str, session: &mut Session) {
+        let usage = || {
+            let (name, _, args, _) = COMMANDS.iter().find(|(_, c, ..)| c == cmd).unwrap();
+            println!("usage: {} {}", name, args);
+        };
         match cmd {
+            MetaCommand::Tokens
+            | MetaCommand::Ast
+            | MetaCommand::Type
+            | MetaCommand::Time
+            | MetaCommand::Load
+            | MetaCommand::Save
+                if arg.is_empty() =>
+            {
+                usage();
+            }
+            MetaCommand::Tokens => println!("{}", inspect::tokens(arg)),
+            MetaCommand::Ast => {
+                let mut parser = Parser::new(Lexer::new(arg));
+                let prog = parser.parse_program();
+                if !parser.errors.is_empty() {
+                    println!("{}", parser.errors.join("\n"));
+                    return;
+                }
+                println!("{}", prog.to_string());
+                println!("{}", inspect::tree(&prog));
+            }
+            MetaCommand::Env => {
+                println!("{}", inspect::bindings(&session.interpreter.env().borrow()))
+            }
+            // like any other input, so that `.save` keeps their bindings
+            MetaCommand::Type => match session.eval(arg) {
+                Ok(v) => println!("{}", v.type_name()),
+                Err(e) => self.print_error(&Repl::error_text(&e)),
+            },
+            MetaCommand::Time => {
+                let start = Instant::now();
+                let result = session.eval(arg);
+                let elapsed = start.elapsed();
+                self.print_result(result);
+                println!(
+                    "time: {:?}, steps: {}",
+                    elapsed,
+                    session.interpreter.steps()
+                );
+            }
+            MetaCommand::Load => match session.load(arg) {
+                Ok(v) => self.print_result(Ok(v)),
+                Err(errors) => self.print_error(&errors.join("\n")),
+            },
+            MetaCommand::Save => {
+                if let Err(e) = session.save(arg) {
+                    self.print_error(&format!("{}: {}", arg, e));
+                }
+            }
             MetaCommand::Help => {
                 Repl::print_help();
             }
@@ -93,23 +360,21 @@ impl Repl {
             MetaCommand::Ping => {
                 println!("pong");
             }
-            MetaCommand::Exit => {}
+            MetaCommand::Cancel | MetaCommand::Reset | MetaCommand::Exit => {}
         }
     }
 
     fn input_is_meta_command(input: &str) -> bool {
-        std::str::from_utf8(input.as_bytes().get(0..1).unwrap()).unwrap() == "."
+        input.starts_with('.')
     }
 
-    fn read_input() -> io::Result<String> {
-        let mut input = String::new();
-        let _ = io::stdin().read_line(&mut input)?;
-        Ok(input.trim().to_string())
-    }
-
-    fn print_prompt() {
-        print!("> ");
-        io::stdout().flush();
+    // `..` asks for the rest of an incomplete program.
+    fn prompt(continuation: bool) -> &'static str {
+        if continuation {
+            ".. "
+        } else {
+            "> "
+        }
     }
 
     fn clear_repl() {
@@ -124,25 +389,86 @@ impl Repl {
     }
 
     fn print_help() {
-        let help = vec![
-            (".clear", "Clear the REPL"),
-            (".exit", "Exit the REPL"),
-            (".help", "Print this help message"),
-            (".ping", "Print \"pong\""),
-        ];
-
         const PADDING: usize = 3;
-        let max_cmd_len = help
+        let usage = |name: &str, args: &str| format!("{} {}", name, args);
+        let max_cmd_len = COMMANDS
             .iter()
-            .map(|(cmd, _)| cmd.len())
+            .map(|(cmd, _, args, _)| usage(cmd, args).len())
             .max()
             .unwrap_or_default();
         let padding_size = PADDING + max_cmd_len;
 
-        for (cmd, msg) in help {
-            println!("{:<width$}{}", cmd, msg, width = padding_size);
+        for (cmd, _, args, msg) in COMMANDS {
+            println!("{:<width$}{}", usage(cmd, args), msg, width = padding_size);
         }
 
         println!();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Interpreter;
+
+    #[test]
+    fn test_completions() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .eval("let printer = 1; let f = fn() { let private = 2; };")
+            .unwrap();
+        let env = interpreter.env().borrow();
+
+        let tests = vec![
+            ("pri", vec!["print", "printer"]),
+            ("re", vec!["read_file", "return"]),
+            ("wh", vec!["while"]),
+            (".c", vec![".cancel", ".clear"]),
+            ("zz", vec![]),
+        ];
+        for (word, expected) in tests {
+            assert_eq!(Repl::completions(word, &env), expected, "word: {}", word);
+        }
+    }
+
+    #[test]
+    fn test_incomplete_input() {
+        let tests = vec![
+            ("let f = fn(x) {", true),
+            ("let f = fn(x) {\n  x * 2\n};", false),
+            ("puts(1,", true),
+            ("if (a) { 1 } else {", true),
+            ("let s = \"open", true),
+            ("let s = \"spans\nlines\";", false),
+            ("let x =", true),
+            ("1 + 2", false),
+            ("let x = 1;", false),
+            ("fn(x) { x }}", false),
+            ("let = 1;", false),
+            ("// just a comment", false),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(Repl::is_incomplete(input), expected, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_meta_command_detection() {
+        let tests = vec![
+            ("", false),
+            (".exit", true),
+            ("let x = 1;", false),
+            ("x.y", false),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                Repl::input_is_meta_command(input),
+                expected,
+                "input: {:?}",
+                input
+            );
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{app, optimize::OptLevel, Error, Interpreter, IoPolicy, Value};
+use std::{fs, io, path::Path};
+
+/// The interpreter of a REPL session and the inputs it evaluated
+/// successfully, which `.save` writes out.
+pub struct Session {
+    pub interpreter: Interpreter,
+    inputs: Vec<String>,
+}
+
+impl Session {
+    pub fn new(opt_level: OptLevel) -> Self {
+        Session {
+            interpreter: Interpreter::new()
+                .with_limits(app::limits())
+                .with_opt_level(opt_level)
+                .with_io_policy(IoPolicy::allow_all()),
+            inputs: vec![],
+        }
+    }
+
+    /// Evaluates `input`, remembering it if it succeeds.
+    pub fn eval(&mut self, input: &str) -> Result<Value, Error> {
+        let value = self.interpreter.eval(input)?;
+        self.inputs.push(input.to_string());
+        Ok(value)
+    }
+
+    /// Evaluates the program in `path` into the session. Errors come back
+    /// one per line, prefixed with the file name.
+    pub fn load(&mut self, path: &str) -> Result<Value, Vec<String>> {
+        let input = fs::read_to_string(path).map_err(|e| vec![format!("{}: {}", path, e)])?;
+        self.eval(&input).map_err(|e| {
+            let errors = match e {
+                Error::Parse(errors) | Error::Resolve(errors) => errors,
+                e => vec![e.to_string()],
+            };
+            errors.iter().map(|e| format!("{}: {}", path, e)).collect()
+        })
+    }
+
+    /// Writes the inputs evaluated so far to `path`, so that loading it
+    /// again rebuilds the bindings of the session.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut contents = String::new();
+        for input in &self.inputs {
+            contents.push_str(input.trim_end());
+            contents.push('\n');
+        }
+        fs::write(path, contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch_dir;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = scratch_dir("session");
+        let saved = dir.join("saved.mk");
+
+        let mut session = Session::new(OptLevel::default());
+        session.eval("let a = 1;").unwrap();
+        session.eval("1 + true").unwrap_err();
+        session.eval("let double = fn(x) {\n  x * 2\n};").unwrap();
+        session.eval("missing").unwrap_err();
+        session.save(&saved).unwrap();
+        assert_eq!(
+            fs::read_to_string(&saved).unwrap(),
+            "let a = 1;\nlet double = fn(x) {\n  x * 2\n};\n"
+        );
+
+        let mut session = Session::new(OptLevel::default());
+        let path = saved.to_str().unwrap();
+        session.load(path).unwrap();
+        assert_eq!(session.eval("double(a)").unwrap().to_string(), "2");
+
+        let broken = dir.join("broken.mk");
+        fs::write(&broken, "let x = 1;\nlet y = x + true;\n").unwrap();
+        let path = broken.to_str().unwrap();
+        assert_eq!(
+            session.load(path).unwrap_err(),
+            vec![format!(
+                "{}: line 2: type mismatch: INTEGER + BOOLEAN",
+                path
+            )]
+        );
+
+        let missing = dir.join("missing.mk");
+        let errors = session.load(missing.to_str().unwrap()).unwrap_err();
+        assert!(
+            errors[0].starts_with(missing.to_str().unwrap()),
+            "{:?}",
+            errors
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
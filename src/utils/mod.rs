@@ -8,3 +8,14 @@ pub fn setup(input: &str) -> (Parser, Node) {
     let prog = parser.parse_program();
     (parser, prog)
 }
+
+/// Creates an empty directory named after `name` and the process in the
+/// temporary directory, for tests that need files. Tests remove it when
+/// they are done.
+#[cfg(test)]
+pub fn scratch_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,27 @@
+//! Runs the `interpreter-rust` binary the way a user would.
+
+use std::{env, fs, process::Command};
+
+#[test]
+fn runaway_recursion_fails_cleanly() {
+    let file = env::temp_dir().join(format!(
+        "interpreter-rust-recursion-{}.mk",
+        std::process::id()
+    ));
+    fs::write(&file, "let f = fn(n) { f(n + 1) }; f(0);").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_interpreter-rust"))
+        .arg("run")
+        .arg(&file)
+        .output()
+        .unwrap();
+    fs::remove_file(&file).unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "stderr: {}", stderr);
+    assert!(
+        stderr.contains("resource exhausted: stack"),
+        "stderr: {}",
+        stderr
+    );
+}
@@ -0,0 +1,188 @@
+//! Conformance suite for the language. Every program in
+//! `tests/conformance/` is run and what it prints is compared with the
+//! `.out` file next to it, or with nothing when there is none.
+//!
+//! A program that is expected to fail marks the offending line with a
+//! comment holding part of the message:
+//!
+//! ```text
+//! 1 + true; // error: type mismatch
+//! ```
+//!
+//! Every annotation has to match an error reported on its line, and every
+//! error reported has to be on an annotated line. Only the first parse
+//! error counts, the ones after it tend to follow from it. Output printed
+//! before a runtime error is still compared.
+//!
+//! Run a subset with `cargo test --test conformance -- <filter>`.
+
+use interpreter_rust::{Error, Interpreter, IoPolicy, OutputBuffer};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+const ANNOTATION: &str = "// error:";
+
+struct Expected {
+    line: usize,
+    message: String,
+}
+
+struct Reported {
+    line: Option<usize>,
+    message: String,
+}
+
+fn annotations(source: &str) -> Vec<Expected> {
+    source
+        .lines()
+        .enumerate()
+        .filter_map(|(idx, text)| {
+            let (_, message) = text.split_once(ANNOTATION)?;
+            Some(Expected {
+                line: idx + 1,
+                message: message.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+// Errors carry their line as a `line N: ` prefix.
+fn reported(message: String) -> Reported {
+    let line = message
+        .strip_prefix("line ")
+        .and_then(|rest| rest.split_once(": "))
+        .and_then(|(line, rest)| Some((line.parse().ok()?, rest)));
+    match line {
+        Some((line, rest)) => Reported {
+            line: Some(line),
+            message: rest.to_string(),
+        },
+        None => Reported {
+            line: None,
+            message,
+        },
+    }
+}
+
+fn run(source: &str) -> (String, Vec<Reported>) {
+    let output = OutputBuffer::new();
+    let mut interpreter = Interpreter::new()
+        .with_io_policy(IoPolicy {
+            stdout: true,
+            ..IoPolicy::deny_all()
+        })
+        .with_output(output.clone())
+        .with_input(&b""[..]);
+
+    let errors = match interpreter.eval(source) {
+        Ok(_) => vec![],
+        Err(Error::Parse(errors)) => errors.into_iter().take(1).collect(),
+        Err(Error::Resolve(errors)) => errors,
+        Err(err) => vec![err.to_string()],
+    };
+    (
+        output.contents(),
+        errors.into_iter().map(reported).collect(),
+    )
+}
+
+fn check(program: &Path) -> Result<(), String> {
+    let source = fs::read_to_string(program).map_err(|e| e.to_string())?;
+    let expected_output = fs::read_to_string(program.with_extension("out")).unwrap_or_default();
+    let expected = annotations(&source);
+    let (output, errors) = run(&source);
+
+    let mut problems = vec![];
+    for annotation in &expected {
+        let found = errors.iter().any(|err| {
+            err.line == Some(annotation.line) && err.message.contains(&annotation.message)
+        });
+        if !found {
+            problems.push(format!(
+                "line {}: expected error containing {:?}",
+                annotation.line, annotation.message
+            ));
+        }
+    }
+    for err in &errors {
+        let annotated = expected
+            .iter()
+            .any(|annotation| err.line == Some(annotation.line));
+        if !annotated {
+            let line = err.line.map_or("?".to_string(), |line| line.to_string());
+            problems.push(format!("line {}: unexpected error: {}", line, err.message));
+        }
+    }
+    if output != expected_output {
+        problems.push(format!(
+            "output differs\n--- expected\n{}--- actual\n{}",
+            expected_output, output
+        ));
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join("\n"))
+    }
+}
+
+fn programs(dir: &Path) -> Vec<PathBuf> {
+    let mut programs: Vec<_> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("{}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "mk"))
+        .collect();
+    programs.sort();
+    programs
+}
+
+fn main() -> ExitCode {
+    // flags meant for the default test harness are ignored
+    let filters: Vec<String> = env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .collect();
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance");
+
+    let selected: Vec<PathBuf> = programs(&dir)
+        .into_iter()
+        .filter(|program| {
+            let name = program.file_stem().unwrap().to_string_lossy();
+            filters.is_empty() || filters.iter().any(|filter| name.contains(filter.as_str()))
+        })
+        .collect();
+
+    println!("\nrunning {} conformance programs", selected.len());
+    let mut failures = vec![];
+    for program in &selected {
+        let name = program.file_stem().unwrap().to_string_lossy().into_owned();
+        match check(program) {
+            Ok(()) => println!("test {} ... ok", name),
+            Err(problems) => {
+                println!("test {} ... FAILED", name);
+                failures.push((name, problems));
+            }
+        }
+    }
+
+    for (name, problems) in &failures {
+        println!("\n---- {} ----\n{}", name, problems);
+    }
+    let result = if failures.is_empty() { "ok" } else { "FAILED" };
+    println!(
+        "\ntest result: {}. {} passed; {} failed\n",
+        result,
+        selected.len() - failures.len(),
+        failures.len()
+    );
+
+    if failures.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
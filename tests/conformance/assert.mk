@@ -0,0 +1,5 @@
+// assertions pass silently and fail with a runtime error
+assert(1 < 2);
+assert_eq("a" + "b", "ab");
+puts("passed");
+assert_eq(1 + 1, 3); // error: assertion failed: left == right
@@ -0,0 +1,5 @@
+// comparisons, equality and negation
+puts(true, false, !true, !!false);
+puts(1 < 2, 2 < 1, 3 > 2, 1 == 1, 1 != 1);
+puts(true == true, true != false, (1 < 2) == true);
+puts(!5, !null, !0);
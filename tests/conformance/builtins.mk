@@ -0,0 +1,6 @@
+// puts prints each argument on its own line, print joins them with spaces
+puts(1, "two", true);
+print("a", 1, null);
+print("\n");
+puts();
+puts(puts);
@@ -0,0 +1,12 @@
+// functions capture the environment they are defined in
+let adder = fn(x) { fn(y) { x + y } };
+let add_two = adder(2);
+let add_ten = adder(10);
+puts(add_two(3), add_ten(3));
+let counter = fn() {
+    let n = 0;
+    fn() { n + 1 }
+};
+puts(counter()());
+let outer = fn(a) { fn(b) { fn(c) { a + b + c } } };
+puts(outer(1)(2)(3));
@@ -0,0 +1,4 @@
+// a comment on its own line
+puts(1); // a comment after a statement
+// puts(2);
+puts("// not a comment");
@@ -0,0 +1,13 @@
+// if / else and truthiness
+puts(if (true) { 1 } else { 2 });
+puts(if (false) { 1 } else { 2 });
+puts(if (1 < 2) { "yes" } else { "no" });
+puts(if (0) { "zero is truthy" });
+puts(if ("") { "strings are truthy" });
+puts(if (null) { 1 } else { "null is falsy" });
+let sign = fn(n) {
+    if (n < 0) { return -1; }
+    if (n > 0) { return 1; }
+    0
+};
+puts(sign(-5), sign(0), sign(5));
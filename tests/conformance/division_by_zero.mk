@@ -0,0 +1,2 @@
+puts(10 / 5);
+10 / 0; // error: division by zero
@@ -0,0 +1,8 @@
+// function literals, calls and implicit results
+let add = fn(a, b) { a + b };
+puts(add(1, 2));
+puts(fn(x) { x * x }(7));
+let twice = fn(f, x) { f(f(x)) };
+puts(twice(fn(x) { x + 3 }, 10));
+let noop = fn() { 1; 2; 3 };
+puts(noop());
@@ -0,0 +1,5 @@
+// integer literals and arithmetic
+puts(5, -10, 0);
+puts(1 + 2 * 3, (1 + 2) * 3, 10 - 4 - 3);
+puts(7 / 2, -7 / 2, 2 * -3);
+puts(2147483647, -2147483647 - 1);
@@ -0,0 +1,8 @@
+// bindings, shadowing and rebinding
+let a = 5;
+let b = a * 2;
+puts(a, b);
+let a = a + b;
+puts(a);
+let f = fn() { let a = 100; a };
+puts(f(), a);
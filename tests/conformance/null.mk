@@ -0,0 +1,6 @@
+// null, and the value of expressions that produce nothing
+puts(null);
+puts(null == null);
+puts(if (false) { 1 });
+let f = fn() {};
+puts(f());
@@ -0,0 +1,3 @@
+let max = 2147483647;
+puts(max);
+max + 1; // error: integer overflow
@@ -0,0 +1,2 @@
+let x = 1;
+let = 2; // error: Expected peek_token to be =
@@ -0,0 +1,2 @@
+// the suite only allows writing to stdout
+read_file("secret.txt"); // error: permission denied: read secret.txt
@@ -0,0 +1,7 @@
+// operator precedence and grouping
+puts(1 + 2 * 3 - 4 / 2);
+puts(-2 * 3, -(2 * 3), !(1 < 2));
+puts(1 + 2 < 4, 5 > 2 == true, 1 < 2 == 2 < 3);
+puts((5 + 5) * 2 / (1 + 1));
+let f = fn(x) { fn(y) { x * y } };
+puts(f(2)(3) + 1);
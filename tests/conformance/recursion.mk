@@ -0,0 +1,5 @@
+// functions can call themselves and each other
+let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } };
+puts(fact(10));
+let fib = fn(n) { if (n < 2) { return n; } fib(n - 1) + fib(n - 2) };
+puts(fib(15));
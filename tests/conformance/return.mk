@@ -0,0 +1,13 @@
+// return leaves the innermost function, from any depth
+let f = fn() {
+    if (true) {
+        if (true) { return 1; }
+        return 2;
+    }
+    3
+};
+puts(f());
+let g = fn() { let h = fn() { return 1; }; h() + 1 };
+puts(g());
+return 5;
+puts("unreachable");
@@ -0,0 +1,9 @@
+// string literals, escapes, concatenation and comparison
+puts("hello");
+puts("tab\there", "quote \"q\"", "back\\slash");
+puts("con" + "cat" + "enation");
+puts("a" == "a", "a" != "a", "a" == "b");
+print("no", "newline");
+puts("");
+puts("spans
+two lines");
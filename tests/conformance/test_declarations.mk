@@ -0,0 +1,6 @@
+// test declarations only run under the `test` command
+test "never run here" {
+    puts("unreachable");
+    assert(false);
+}
+puts("declared");
@@ -0,0 +1,3 @@
+puts("before");
+1 + true; // error: type mismatch: INTEGER + BOOLEAN
+puts("after");
@@ -0,0 +1,2 @@
+let a = 1;
+puts(a + b); // error: identifier not found: b
@@ -0,0 +1 @@
+true + false; // error: unknown operator: BOOLEAN + BOOLEAN
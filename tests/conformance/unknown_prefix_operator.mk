@@ -0,0 +1 @@
+-"text"; // error: unknown operator: -STRING
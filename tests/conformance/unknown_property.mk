@@ -0,0 +1,2 @@
+let n = 5;
+n.size; // error: unknown property: INTEGER.size
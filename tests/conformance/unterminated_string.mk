@@ -0,0 +1,2 @@
+puts("fine");
+let s = "never closed; // error: unterminated string literal
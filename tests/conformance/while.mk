@@ -0,0 +1,17 @@
+// while loops
+let i = 0;
+let sum = 0;
+while (i < 5) {
+    let sum = sum + i;
+    let i = i + 1;
+}
+puts(sum);
+let first_square_over = fn(limit) {
+    let n = 1;
+    while (true) {
+        if (n * n > limit) { return n; }
+        let n = n + 1;
+    }
+};
+puts(first_square_over(50));
+puts(while (false) { 1 });
@@ -0,0 +1,57 @@
+//! Runs every program in `examples/` and compares what it prints with the
+//! `.out` file next to it.
+
+use interpreter_rust::{Interpreter, IoPolicy, OutputBuffer};
+use std::{fs, path::Path};
+
+fn run(source: &str) -> Result<String, String> {
+    let output = OutputBuffer::new();
+    let mut interpreter = Interpreter::new()
+        .with_io_policy(IoPolicy {
+            stdout: true,
+            ..IoPolicy::deny_all()
+        })
+        .with_output(output.clone())
+        .with_input(&b""[..]);
+    interpreter.eval(source).map_err(|e| e.to_string())?;
+    Ok(output.contents())
+}
+
+#[test]
+fn examples_match_golden_output() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let mut programs: Vec<_> = fs::read_dir(&dir)
+        .expect("examples directory should exist")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "mk"))
+        .collect();
+    programs.sort();
+    assert!(!programs.is_empty(), "no examples in {}", dir.display());
+
+    let mut failures = vec![];
+    for program in &programs {
+        let source = fs::read_to_string(program).unwrap();
+        let golden = program.with_extension("out");
+        let expected =
+            fs::read_to_string(&golden).unwrap_or_else(|e| panic!("{}: {}", golden.display(), e));
+
+        match run(&source) {
+            Ok(actual) if actual == expected => {}
+            Ok(actual) => failures.push(format!(
+                "{}: output differs\n--- expected\n{}--- actual\n{}",
+                program.display(),
+                expected,
+                actual
+            )),
+            Err(e) => failures.push(format!("{}: {}", program.display(), e)),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} examples failed:\n\n{}",
+        failures.len(),
+        programs.len(),
+        failures.join("\n\n")
+    );
+}
@@ -0,0 +1,11 @@
+// one passing and one failing test, for the `test` command
+let double = fn(x) { x * 2 };
+
+test "doubles" {
+    assert_eq(double(2), 4);
+}
+
+test "checks" {
+    let check = fn(x) { assert_eq(double(x), 5) };
+    check(2);
+}